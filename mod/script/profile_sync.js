@@ -0,0 +1,61 @@
+(() => {
+  const ALIAS_KEY = 'ssm_alias';
+  const SETTINGS_KEY = 'sugarcube-settings';
+  const MOD_LOADER_KEY = 'modloader';
+
+  function profileUrl(key) {
+    const alias = encodeURIComponent(localStorage.getItem(ALIAS_KEY) || 'anonymous');
+    return key === undefined ? `save-sync/profile/${alias}` : `save-sync/profile/${alias}/${key}`;
+  }
+
+  async function list() {
+    const resp = await fetch(profileUrl());
+    return resp.ok ? resp.json() : [];
+  }
+
+  async function get(key) {
+    const resp = await fetch(profileUrl(key));
+    return resp.ok ? resp.text() : null;
+  }
+
+  async function put(key, value) {
+    const resp = await fetch(profileUrl(key), { method: 'PUT', body: value });
+    return resp.ok;
+  }
+
+  async function del(key) {
+    const resp = await fetch(profileUrl(key), { method: 'DELETE' });
+    return resp.ok;
+  }
+
+  // ModLoader keeps its enabled and disabled mod lists in localStorage entries named after itself.
+  function modLoaderEntries() {
+    return Object.keys(localStorage)
+      .filter(key => /modloader/i.test(key))
+      .reduce((entries, key) => ({ ...entries, [key]: localStorage.getItem(key) }), {});
+  }
+
+  async function push() {
+    const settings = SugarCube.storage.get('settings');
+    const results = await Promise.all([
+      put(SETTINGS_KEY, JSON.stringify(settings ?? {})),
+      put(MOD_LOADER_KEY, JSON.stringify(modLoaderEntries())),
+    ]);
+    return results.every(ok => ok);
+  }
+
+  // Both SugarCube and ModLoader read their state on startup, the page has to be reloaded after pulling.
+  async function pull() {
+    const [settings, modLoader] = await Promise.all([get(SETTINGS_KEY), get(MOD_LOADER_KEY)]);
+    if (settings === null && modLoader === null) return false;
+    if (settings !== null) SugarCube.storage.set('settings', JSON.parse(settings));
+    if (modLoader !== null) {
+      Object.entries(JSON.parse(modLoader)).forEach(([key, value]) =>
+        localStorage.setItem(key, value)
+      );
+    }
+    return true;
+  }
+
+  window.ssmProfile = { list, get, put, del, push, pull };
+})();
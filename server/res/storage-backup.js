@@ -0,0 +1,166 @@
+(() => {
+  const ENDPOINT = 'storage-backup';
+  const BACKUP_INTERVAL = 60 * 1000;
+
+  let lastBackup = null;
+
+  function request(req) {
+    return new Promise((resolve, reject) => {
+      req.onsuccess = () => resolve(req.result);
+      req.onerror = () => reject(req.error);
+    });
+  }
+
+  function snapshotLocalStorage() {
+    const entries = {};
+    for (let i = 0; i < localStorage.length; i++) {
+      const key = localStorage.key(i);
+      entries[key] = localStorage.getItem(key);
+    }
+    return entries;
+  }
+
+  async function snapshotDatabase(name) {
+    const db = await request(indexedDB.open(name));
+    try {
+      const stores = {};
+      for (const storeName of db.objectStoreNames) {
+        const store = db.transaction(storeName, 'readonly').objectStore(storeName);
+        const [keys, values] = await Promise.all([
+          request(store.getAllKeys()),
+          request(store.getAll()),
+        ]);
+        stores[storeName] = {
+          keyPath: store.keyPath,
+          autoIncrement: store.autoIncrement,
+          indexes: Array.from(store.indexNames).map(indexName => {
+            const index = store.index(indexName);
+            return {
+              name: index.name,
+              keyPath: index.keyPath,
+              unique: index.unique,
+              multiEntry: index.multiEntry,
+            };
+          }),
+          records: keys.map((key, i) => ({ key, value: values[i] })),
+        };
+      }
+      return { version: db.version, stores };
+    } finally {
+      db.close();
+    }
+  }
+
+  async function snapshotIndexedDB() {
+    // Browsers without indexedDB.databases() cannot enumerate databases, only localStorage is kept there.
+    if (!indexedDB.databases) return {};
+    const databases = {};
+    for (const { name } of await indexedDB.databases()) {
+      if (name) databases[name] = await snapshotDatabase(name);
+    }
+    return databases;
+  }
+
+  async function restoreDatabase(name, snapshot) {
+    await request(indexedDB.deleteDatabase(name));
+    const open = indexedDB.open(name, snapshot.version);
+    open.onupgradeneeded = () => {
+      Object.entries(snapshot.stores).forEach(([storeName, store]) => {
+        const created = open.result.createObjectStore(storeName, {
+          keyPath: store.keyPath,
+          autoIncrement: store.autoIncrement,
+        });
+        store.indexes.forEach(index =>
+          created.createIndex(index.name, index.keyPath, {
+            unique: index.unique,
+            multiEntry: index.multiEntry,
+          })
+        );
+      });
+    };
+    const db = await request(open);
+    try {
+      for (const [storeName, store] of Object.entries(snapshot.stores)) {
+        const tx = db.transaction(storeName, 'readwrite');
+        const objectStore = tx.objectStore(storeName);
+        store.records.forEach(({ key, value }) =>
+          store.keyPath === null ? objectStore.put(value, key) : objectStore.put(value)
+        );
+        await new Promise((resolve, reject) => {
+          tx.oncomplete = resolve;
+          tx.onerror = () => reject(tx.error);
+        });
+      }
+    } finally {
+      db.close();
+    }
+  }
+
+  async function backup(keepalive = false) {
+    const snapshot = JSON.stringify({
+      localStorage: snapshotLocalStorage(),
+      indexedDB: await snapshotIndexedDB(),
+    });
+    if (snapshot === lastBackup) return;
+    const resp = await fetch(ENDPOINT, {
+      method: 'PUT',
+      headers: { 'Content-Type': 'application/json' },
+      body: snapshot,
+      keepalive,
+    });
+    if (resp.ok) {
+      lastBackup = snapshot;
+    } else {
+      console.warn(`[storage-backup] backup failed: ${resp.status}`);
+    }
+  }
+
+  async function restore() {
+    const resp = await fetch(ENDPOINT);
+    if (!resp.ok) return false;
+    const snapshot = await resp.json();
+    localStorage.clear();
+    Object.entries(snapshot.localStorage || {}).forEach(([key, value]) =>
+      localStorage.setItem(key, value)
+    );
+    for (const [name, database] of Object.entries(snapshot.indexedDB || {})) {
+      await restoreDatabase(name, database);
+    }
+    return true;
+  }
+
+  function addButton() {
+    const button = document.createElement('button');
+    button.textContent = 'Restore Backup';
+    button.style.cssText = 'position: fixed; bottom: 8px; right: 8px; z-index: 2147483647; opacity: 0.6;';
+    button.onclick = async () => {
+      if (!confirm('Replace the local game data with the server backup and reload?')) return;
+      if (await restore()) {
+        location.reload();
+      } else {
+        alert('No backup found on the server.');
+      }
+    };
+    document.body.appendChild(button);
+  }
+
+  async function init() {
+    // A fresh browser has no data yet, bring the server backup in before the player starts over.
+    if (localStorage.length === 0 && sessionStorage.getItem('storage-backup-restored') === null) {
+      sessionStorage.setItem('storage-backup-restored', '1');
+      if (await restore()) {
+        location.reload();
+        return;
+      }
+    }
+    addButton();
+    setInterval(() => backup().catch(e => console.warn('[storage-backup] backup failed', e)), BACKUP_INTERVAL);
+    window.addEventListener('pagehide', () => backup(true).catch(() => {}));
+  }
+
+  if (document.readyState === 'loading') {
+    document.addEventListener('DOMContentLoaded', init);
+  } else {
+    init();
+  }
+})();
@@ -0,0 +1,74 @@
+(prefix => {
+  function isolateStorage(storage) {
+    const ownKeys = () => {
+      const keys = [];
+      for (let i = 0; i < storage.length; i++) {
+        const key = storage.key(i);
+        if (key.startsWith(prefix)) keys.push(key.slice(prefix.length));
+      }
+      return keys;
+    };
+    const api = {
+      getItem: key => storage.getItem(prefix + key),
+      setItem: (key, value) => storage.setItem(prefix + key, String(value)),
+      removeItem: key => storage.removeItem(prefix + key),
+      clear: () => ownKeys().forEach(key => storage.removeItem(prefix + key)),
+      key: index => ownKeys()[index] ?? null,
+      get length() {
+        return ownKeys().length;
+      },
+    };
+    // Games also use storage as a plain object, e.g. `localStorage.foo` or `Object.keys(localStorage)`.
+    return new Proxy(api, {
+      get: (target, prop) =>
+        prop in target
+          ? target[prop]
+          : typeof prop === 'string'
+            ? (storage.getItem(prefix + prop) ?? undefined)
+            : undefined,
+      set: (target, prop, value) => {
+        target.setItem(prop, value);
+        return true;
+      },
+      deleteProperty: (target, prop) => {
+        target.removeItem(prop);
+        return true;
+      },
+      has: (target, prop) =>
+        prop in target || (typeof prop === 'string' && storage.getItem(prefix + prop) !== null),
+      ownKeys: () => ownKeys(),
+      getOwnPropertyDescriptor: (target, prop) => {
+        if (typeof prop !== 'string') return undefined;
+        const value = storage.getItem(prefix + prop);
+        return value === null
+          ? undefined
+          : { value, writable: true, enumerable: true, configurable: true };
+      },
+    });
+  }
+
+  function isolateIndexedDB(factory) {
+    const open = factory.open.bind(factory);
+    const deleteDatabase = factory.deleteDatabase.bind(factory);
+    factory.open = (name, version) =>
+      version === undefined ? open(prefix + name) : open(prefix + name, version);
+    factory.deleteDatabase = name => deleteDatabase(prefix + name);
+    if (factory.databases) {
+      const databases = factory.databases.bind(factory);
+      factory.databases = async () =>
+        (await databases())
+          .filter(db => db.name && db.name.startsWith(prefix))
+          .map(db => ({ ...db, name: db.name.slice(prefix.length) }));
+    }
+  }
+
+  for (const name of ['localStorage', 'sessionStorage']) {
+    try {
+      const isolated = isolateStorage(window[name]);
+      Object.defineProperty(window, name, { value: isolated, configurable: true });
+    } catch (e) {
+      console.warn(`[storage-isolation] unable to isolate ${name}`, e);
+    }
+  }
+  if (window.indexedDB) isolateIndexedDB(window.indexedDB);
+})(__STORAGE_PREFIX__);
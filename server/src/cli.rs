@@ -0,0 +1,435 @@
+use crate::foundation::api_token::{ApiTokenStore, TokenPermission};
+use crate::foundation::audit::{Actor, AuditAction, AuditLog, AuditTarget};
+use crate::foundation::config::Config;
+use crate::foundation::gc::{GcMode, GcReport, collect_unused_assets, find_unused_assets};
+use crate::foundation::household::HouseholdStore;
+use crate::foundation::registry::load_configured_game;
+use crate::foundation::release::import_release;
+use crate::foundation::user::{SessionStore, UserStore};
+use anyhow::{Result, anyhow};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Serves the games when started without a command.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Manage user accounts
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+    /// Manage API tokens
+    Token {
+        #[command(subcommand)]
+        command: TokenCommand,
+    },
+    /// Manage household profiles
+    Household {
+        #[command(subcommand)]
+        command: HouseholdCommand,
+    },
+    /// Import game releases
+    Release {
+        #[command(subcommand)]
+        command: ReleaseCommand,
+    },
+    /// Report the indexes, layers and mods no instance uses, and optionally collect them
+    Gc {
+        #[arg(long)]
+        game: String,
+        /// Collect the reported assets after confirmation
+        #[arg(long, value_enum)]
+        mode: Option<GcMode>,
+        /// Skip the confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// Create a user, the password is prompted for when not given
+    Add {
+        name: String,
+        #[arg(long)]
+        admin: bool,
+        /// Group the user belongs to, may be repeated
+        #[arg(long = "group")]
+        groups: Vec<String>,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Change the password of a user
+    Passwd {
+        name: String,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Replace the groups of a user
+    Groups { name: String, groups: Vec<String> },
+    /// Delete a user
+    Remove { name: String },
+    /// List all users
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum TokenCommand {
+    /// Create a token and print its secret, which is not shown again
+    Create {
+        name: String,
+        /// Game the token is scoped to, may be repeated, every game when omitted
+        #[arg(long = "game")]
+        games: Vec<String>,
+        /// Permission of the token, may be repeated
+        #[arg(long = "permission", value_enum, required = true)]
+        permissions: Vec<TokenPermission>,
+    },
+    /// List all tokens
+    List,
+    /// Revoke a token by its id
+    Revoke { id: String },
+}
+
+#[derive(Subcommand)]
+pub enum HouseholdCommand {
+    /// Create a profile
+    Add {
+        name: String,
+        /// PIN of 4 to 8 digits asked for when picking the profile
+        #[arg(long)]
+        pin: Option<String>,
+        /// Avatar color as #rrggbb, derived from the name when omitted
+        #[arg(long)]
+        color: Option<String>,
+    },
+    /// Delete a profile, its saves are kept
+    Remove { name: String },
+    /// List all profiles
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum ReleaseCommand {
+    /// Import a zip, tar or tar.gz release holding the game's html file and its img folder
+    Import {
+        archive: PathBuf,
+        #[arg(long)]
+        game: String,
+        /// Id of the index, inferred from the html file or the archive name when omitted
+        #[arg(long)]
+        version: Option<String>,
+        /// Also create an instance with this id using the imported index and layer
+        #[arg(long)]
+        instance: Option<String>,
+    },
+}
+
+pub fn run_command(config: &Config, command: Command) -> Result<()> {
+    let audit = AuditLog::open(&config.get_data_path())?;
+    match command {
+        Command::User { command } => run_user_command(config, &audit, command),
+        Command::Token { command } => run_token_command(config, &audit, command),
+        Command::Household { command } => run_household_command(config, &audit, command),
+        Command::Release { command } => run_release_command(config, &audit, command),
+        Command::Gc { game, mode, yes } => run_gc_command(config, &audit, &game, mode, yes),
+    }
+}
+
+fn audit_target(audit: &AuditLog, action: AuditAction, target: &str) {
+    audit.record(
+        &Actor::cli(),
+        action,
+        AuditTarget {
+            target: Some(target),
+            ..Default::default()
+        },
+    );
+}
+
+fn run_user_command(config: &Config, audit: &AuditLog, command: UserCommand) -> Result<()> {
+    let users = UserStore::load(&config.get_data_path())?;
+
+    match command {
+        UserCommand::Add {
+            name,
+            admin,
+            groups,
+            password,
+        } => {
+            let password = password.map_or_else(prompt_password, Ok)?;
+            users.add(&name, &password, admin, groups)?;
+            audit_target(audit, AuditAction::UserAdd, &name);
+            println!("Created user {}", name);
+        }
+        UserCommand::Passwd { name, password } => {
+            let password = password.map_or_else(prompt_password, Ok)?;
+            users.set_password(&name, &password)?;
+            let sessions = SessionStore::load(&config.get_data_path())?.remove_user(&name)?;
+            audit_target(audit, AuditAction::UserUpdate, &name);
+            println!("Changed password of {}, ended {} sessions", name, sessions);
+        }
+        UserCommand::Groups { name, groups } => {
+            users.set_groups(&name, groups)?;
+            audit_target(audit, AuditAction::UserUpdate, &name);
+            println!("Changed groups of {}", name);
+        }
+        UserCommand::Remove { name } => {
+            users.remove(&name)?;
+            SessionStore::load(&config.get_data_path())?.remove_user(&name)?;
+            audit_target(audit, AuditAction::UserRemove, &name);
+            println!("Removed user {}", name);
+        }
+        UserCommand::List => {
+            for user in users.all() {
+                let role = if user.admin { "admin" } else { "user" };
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    user.name,
+                    role,
+                    user.groups.join(","),
+                    user.created_at
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_token_command(config: &Config, audit: &AuditLog, command: TokenCommand) -> Result<()> {
+    let tokens = ApiTokenStore::load(&config.get_data_path())?;
+
+    match command {
+        TokenCommand::Create {
+            name,
+            games,
+            permissions,
+        } => {
+            let (token, secret) = tokens.create(&name, games, permissions)?;
+            audit_target(audit, AuditAction::TokenCreate, &token.id);
+            println!("Created token {} ({})", token.id, token.name);
+            println!("{}", secret);
+        }
+        TokenCommand::List => {
+            for token in tokens.all() {
+                let permissions: Vec<String> = token
+                    .permissions
+                    .iter()
+                    .filter_map(|permission| permission.to_possible_value())
+                    .map(|value| value.get_name().to_string())
+                    .collect();
+                let games = if token.games.is_empty() {
+                    "*".to_string()
+                } else {
+                    token.games.join(",")
+                };
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    token.id,
+                    token.name,
+                    games,
+                    permissions.join(","),
+                    token.last_used_at.as_deref().unwrap_or("never")
+                );
+            }
+        }
+        TokenCommand::Revoke { id } => {
+            let token = tokens.revoke(&id)?;
+            audit_target(audit, AuditAction::TokenRevoke, &token.id);
+            println!("Revoked token {} ({})", token.id, token.name);
+        }
+    }
+
+    Ok(())
+}
+
+fn run_household_command(
+    config: &Config,
+    audit: &AuditLog,
+    command: HouseholdCommand,
+) -> Result<()> {
+    let households = HouseholdStore::load(&config.get_data_path())?;
+
+    match command {
+        HouseholdCommand::Add { name, pin, color } => {
+            households.add(&name, pin.as_deref(), color.as_deref())?;
+            audit_target(audit, AuditAction::HouseholdAdd, &name);
+            println!("Created profile {}", name);
+        }
+        HouseholdCommand::Remove { name } => {
+            households.remove(&name)?;
+            audit_target(audit, AuditAction::HouseholdRemove, &name);
+            println!("Removed profile {}", name);
+        }
+        HouseholdCommand::List => {
+            for profile in households.all() {
+                let pin = if profile.has_pin() { "pin" } else { "-" };
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    profile.name, profile.color, pin, profile.created_at
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_release_command(config: &Config, audit: &AuditLog, command: ReleaseCommand) -> Result<()> {
+    match command {
+        ReleaseCommand::Import {
+            archive,
+            game,
+            version,
+            instance,
+        } => {
+            let mut game_info = load_configured_game(config, &game)?;
+            let archive_name = archive.file_name().and_then(|name| name.to_str());
+            let imported = import_release(
+                &mut game_info,
+                &archive,
+                archive_name,
+                version.as_deref(),
+                instance.as_deref(),
+            )?;
+            audit.record(
+                &Actor::cli(),
+                AuditAction::ReleaseImport,
+                AuditTarget {
+                    game: Some(&game),
+                    instance: imported.instance.as_deref(),
+                    target: Some(&imported.version),
+                },
+            );
+
+            println!("Imported index {}", imported.version);
+            if let Some(layer) = &imported.layer {
+                println!("Imported layer {}", layer);
+            }
+            if let Some(instance) = &imported.instance {
+                println!("Created instance {}", instance);
+            }
+            println!(
+                "Restart the server or POST /api/games/{}/reload to serve it",
+                game
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn run_gc_command(
+    config: &Config,
+    audit: &AuditLog,
+    game: &str,
+    mode: Option<GcMode>,
+    yes: bool,
+) -> Result<()> {
+    let mut game_info = load_configured_game(config, game)?;
+    let report = find_unused_assets(&game_info)?;
+    print_gc_report(&report);
+    let Some(mode) = mode.filter(|_| !report.assets.is_empty()) else {
+        return Ok(());
+    };
+
+    let verb = match mode {
+        GcMode::Archive => "Archive",
+        GcMode::Delete => "Delete",
+    };
+    if !yes && !prompt_confirm(&format!("{} {} assets?", verb, report.assets.len()))? {
+        println!("Nothing collected");
+        return Ok(());
+    }
+
+    let (collected, _trash) = collect_unused_assets(&mut game_info, report.assets, mode)?;
+    let action = match mode {
+        GcMode::Archive => AuditAction::GcArchive,
+        GcMode::Delete => AuditAction::GcDelete,
+    };
+    for unused in &collected.assets {
+        audit.record(
+            &Actor::cli(),
+            action,
+            AuditTarget {
+                game: Some(game),
+                target: Some(&unused.asset.to_string()),
+                ..Default::default()
+            },
+        );
+    }
+
+    match &collected.archive {
+        Some(archive) => println!(
+            "Archived {} assets to {}",
+            collected.assets.len(),
+            archive.display()
+        ),
+        None => println!("Deleted {} assets", collected.assets.len()),
+    }
+    println!(
+        "Restart the server or POST /api/games/{}/reload to drop them from a running server",
+        game
+    );
+    Ok(())
+}
+
+fn print_gc_report(report: &GcReport) {
+    for unused in &report.assets {
+        println!(
+            "{}\t{}\t{}\t{}",
+            unused.asset.kind.get_name(),
+            unused.asset.id,
+            unused.usage.files,
+            format_bytes(unused.usage.bytes)
+        );
+    }
+    println!(
+        "{} unused assets, {} files, {}",
+        report.assets.len(),
+        report.total.files,
+        format_bytes(report.total.bytes)
+    );
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn prompt_confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn prompt_password() -> Result<String> {
+    let password = rpassword::prompt_password("Password: ")?;
+    if password.is_empty() {
+        return Err(anyhow!("Password must not be empty"));
+    }
+    if rpassword::prompt_password("Repeat password: ")? != password {
+        return Err(anyhow!("Passwords do not match"));
+    }
+    Ok(password)
+}
@@ -0,0 +1,282 @@
+use crate::util::file::WatchedFile;
+use crate::util::token::random_token;
+use anyhow::{Result, anyhow};
+use axum::http::Method;
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+const TOKEN_FILE_NAME: &str = "tokens.json";
+const TOKEN_PREFIX: &str = "dom_";
+const TOKEN_SECRET_BYTES: usize = 32;
+const TOKEN_ID_BYTES: usize = 6;
+/// `last_used_at` is kept in memory on every use but only written out this often.
+const LAST_USED_PERSIST_INTERVAL_SECS: i64 = 60;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenPermission {
+    /// Read games, instances and saves
+    Read,
+    /// Upload, change and delete saves
+    SaveWrite,
+    /// Use the admin API and pass every access control list
+    Admin,
+}
+
+/// What a token may do, attached to the identity of requests authenticated by it.
+#[derive(Clone, Debug)]
+pub struct TokenScope {
+    /// Games the token may access, every game when empty.
+    pub games: Vec<String>,
+    pub permissions: Vec<TokenPermission>,
+}
+
+impl TokenScope {
+    pub fn allows_game(&self, game_id: &str) -> bool {
+        self.games.is_empty() || self.games.iter().any(|game| game == game_id)
+    }
+
+    /// Admin tokens have every permission.
+    pub fn has(&self, permission: TokenPermission) -> bool {
+        self.permissions.contains(&permission) || self.permissions.contains(&TokenPermission::Admin)
+    }
+
+    /// Reading needs `read`, everything else changes data and needs `save-write`.
+    pub fn allows_method(&self, method: &Method) -> bool {
+        if *method == Method::GET || *method == Method::HEAD {
+            self.has(TokenPermission::Read)
+        } else {
+            self.has(TokenPermission::SaveWrite)
+        }
+    }
+}
+
+/// A bearer token, only the SHA-256 hash of its secret is stored.
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing)]
+    secret_hash: String,
+    pub games: Vec<String>,
+    pub permissions: Vec<TokenPermission>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+impl ApiToken {
+    pub fn get_identity_name(&self) -> String {
+        format!("token:{}", self.name)
+    }
+
+    pub fn get_scope(&self) -> TokenScope {
+        TokenScope {
+            games: self.games.clone(),
+            permissions: self.permissions.clone(),
+        }
+    }
+}
+
+/// The stored form of [`ApiToken`], which unlike the API form keeps the secret hash.
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    token: ApiToken,
+    secret_hash: String,
+}
+
+/// API tokens, persisted to `tokens.json` in the data directory.
+pub struct ApiTokenStore {
+    file: WatchedFile,
+    tokens: Mutex<HashMap<String, ApiToken>>,
+}
+
+impl ApiTokenStore {
+    pub fn load(data_path: &Path) -> Result<Self> {
+        let file = WatchedFile::new(data_path.join(TOKEN_FILE_NAME));
+        let tokens = read_tokens(file.get_path())?;
+        info!("Loaded {} API tokens", tokens.len());
+
+        Ok(ApiTokenStore {
+            file,
+            tokens: Mutex::new(tokens),
+        })
+    }
+
+    /// Locks the tokens, reading them again first when a CLI command changed the file.
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, ApiToken>> {
+        let mut tokens = self.tokens.lock().unwrap();
+        if self.file.is_changed() {
+            match read_tokens(self.file.get_path()) {
+                Ok(reloaded) => {
+                    info!("Reloaded {} API tokens", reloaded.len());
+                    *tokens = reloaded;
+                    self.file.update();
+                }
+                Err(err) => error!("Failed to reload {}: {}", TOKEN_FILE_NAME, err),
+            }
+        }
+        tokens
+    }
+
+    /// Creates a token and returns it along with its secret, the secret cannot be recovered later.
+    pub fn create(
+        &self,
+        name: &str,
+        games: Vec<String>,
+        permissions: Vec<TokenPermission>,
+    ) -> Result<(ApiToken, String)> {
+        if name.trim().is_empty() {
+            return Err(anyhow!("Token name must not be empty"));
+        }
+        if permissions.is_empty() {
+            return Err(anyhow!("Token needs at least one permission"));
+        }
+
+        let secret = format!("{}{}", TOKEN_PREFIX, random_token(TOKEN_SECRET_BYTES));
+        let token = ApiToken {
+            id: random_token(TOKEN_ID_BYTES),
+            name: name.trim().to_string(),
+            secret_hash: hash_secret(&secret),
+            games,
+            permissions,
+            created_at: Local::now().to_rfc3339(),
+            last_used_at: None,
+        };
+
+        let mut tokens = self.lock();
+        tokens.insert(token.id.clone(), token.clone());
+        self.persist(&tokens)?;
+
+        Ok((token, secret))
+    }
+
+    pub fn revoke(&self, id: &str) -> Result<ApiToken> {
+        let mut tokens = self.lock();
+        let token = tokens
+            .remove(id)
+            .ok_or_else(|| anyhow!("Token '{}' does not exist", id))?;
+        self.persist(&tokens)?;
+        Ok(token)
+    }
+
+    pub fn all(&self) -> Vec<ApiToken> {
+        let mut tokens: Vec<ApiToken> = self.lock().values().cloned().collect();
+        tokens.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        tokens
+    }
+
+    /// Looks up the token of a bearer secret and records its use.
+    pub fn authenticate(&self, secret: &str) -> Option<ApiToken> {
+        if !secret.starts_with(TOKEN_PREFIX) {
+            return None;
+        }
+        let secret_hash = hash_secret(secret);
+
+        let mut tokens = self.lock();
+        let token = tokens
+            .values_mut()
+            .find(|token| token.secret_hash == secret_hash)?;
+
+        let now = Local::now();
+        let persist_due = token
+            .last_used_at
+            .as_ref()
+            .and_then(|last_used_at| DateTime::parse_from_rfc3339(last_used_at).ok())
+            .is_none_or(|last_used_at| {
+                now.signed_duration_since(last_used_at)
+                    > Duration::seconds(LAST_USED_PERSIST_INTERVAL_SECS)
+            });
+        token.last_used_at = Some(now.to_rfc3339());
+        let token = token.clone();
+
+        if persist_due && let Err(err) = self.persist(&tokens) {
+            error!("Failed to record use of token {}: {}", token.id, err);
+        }
+        Some(token)
+    }
+
+    fn persist(&self, tokens: &HashMap<String, ApiToken>) -> Result<()> {
+        let stored: Vec<StoredToken> = tokens
+            .values()
+            .map(|token| StoredToken {
+                token: token.clone(),
+                secret_hash: token.secret_hash.clone(),
+            })
+            .collect();
+        self.file
+            .write(&serde_json::to_string_pretty(&stored)?)
+            .inspect_err(|err| {
+                error!("Failed to write {}: {}", TOKEN_FILE_NAME, err);
+            })?;
+        Ok(())
+    }
+}
+
+fn read_tokens(path: &Path) -> Result<HashMap<String, ApiToken>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    Ok(
+        serde_json::from_str::<Vec<StoredToken>>(&fs::read_to_string(path)?)?
+            .into_iter()
+            .map(|stored| {
+                let mut token = stored.token;
+                token.secret_hash = stored.secret_hash;
+                (token.id.clone(), token)
+            })
+            .collect(),
+    )
+}
+
+fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(games: &[&str], permissions: Vec<TokenPermission>) -> TokenScope {
+        TokenScope {
+            games: games.iter().map(|game| game.to_string()).collect(),
+            permissions,
+        }
+    }
+
+    #[test]
+    fn token_scope_limits_games() {
+        assert!(scope(&[], vec![TokenPermission::Read]).allows_game("dol"));
+        let scoped = scope(&["dol"], vec![TokenPermission::Read]);
+        assert!(scoped.allows_game("dol"));
+        assert!(!scoped.allows_game("sc"));
+    }
+
+    #[test]
+    fn token_scope_maps_methods_to_permissions() {
+        let read = scope(&[], vec![TokenPermission::Read]);
+        assert!(read.allows_method(&Method::GET));
+        assert!(read.allows_method(&Method::HEAD));
+        assert!(!read.allows_method(&Method::PUT));
+        assert!(!read.has(TokenPermission::Admin));
+
+        let write = scope(&[], vec![TokenPermission::SaveWrite]);
+        assert!(!write.allows_method(&Method::GET));
+        assert!(write.allows_method(&Method::POST));
+
+        let admin = scope(&[], vec![TokenPermission::Admin]);
+        assert!(admin.allows_method(&Method::GET));
+        assert!(admin.allows_method(&Method::DELETE));
+        assert!(admin.has(TokenPermission::SaveWrite));
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+const AUDIT_FILE_NAME: &str = "audit.jsonl";
+const AUDIT_QUERY_DEFAULT_LIMIT: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditAction {
+    SaveUpload,
+    SaveUpdate,
+    SaveDelete,
+    SaveCopy,
+    SaveImport,
+    SaveShare,
+    AutoSave,
+    ProfileWrite,
+    ProfileDelete,
+    StorageBackup,
+    Login,
+    LoginFailed,
+    Logout,
+    AccessDenied,
+    TokenUse,
+    TokenRejected,
+    TokenCreate,
+    TokenRevoke,
+    UserAdd,
+    UserUpdate,
+    UserRemove,
+    HouseholdAdd,
+    HouseholdRemove,
+    HouseholdSelect,
+    InstanceCreate,
+    InstanceUpdate,
+    InstanceDelete,
+    IndexUpload,
+    ModUpload,
+    LayerUpload,
+    ReleaseImport,
+    InboxReject,
+    GcArchive,
+    GcDelete,
+    GameReload,
+}
+
+/// Who performed an audited operation and from where.
+#[derive(Clone, Debug, Default)]
+pub struct Actor {
+    /// User name, `token:{name}` for API tokens or `household:{name}` for household profiles.
+    pub name: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl Actor {
+    /// Operations run on the command line of the server.
+    pub fn cli() -> Self {
+        Actor {
+            name: Some(String::from("cli")),
+            ip: None,
+        }
+    }
+
+    /// Files picked up from the inbox folder of a game.
+    pub fn inbox() -> Self {
+        Actor {
+            name: Some(String::from("inbox")),
+            ip: None,
+        }
+    }
+}
+
+/// What an audited operation acted on, every part is optional.
+#[derive(Default)]
+pub struct AuditTarget<'a> {
+    pub game: Option<&'a str>,
+    pub instance: Option<&'a str>,
+    /// The save, token, user or profile the operation acted on.
+    pub target: Option<&'a str>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct AuditEntry {
+    pub time: String,
+    pub action: AuditAction,
+    pub actor: Option<String>,
+    pub ip: Option<String>,
+    pub game: Option<String>,
+    pub instance: Option<String>,
+    pub target: Option<String>,
+}
+
+#[derive(Deserialize, Default, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    pub action: Option<AuditAction>,
+    pub actor: Option<String>,
+    pub ip: Option<String>,
+    pub game: Option<String>,
+    pub instance: Option<String>,
+    pub target: Option<String>,
+    /// RFC 3339 time, only entries at or after it.
+    pub since: Option<String>,
+    /// RFC 3339 time, only entries before it.
+    pub until: Option<String>,
+    /// Most entries returned, newest first.
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(
+        &self,
+        entry: &AuditEntry,
+        since: Option<DateTime<Local>>,
+        until: Option<DateTime<Local>>,
+    ) -> bool {
+        let field =
+            |filter: &Option<String>, value: &Option<String>| filter.is_none() || filter == value;
+        let time = DateTime::parse_from_rfc3339(&entry.time).ok();
+
+        self.action.is_none_or(|action| action == entry.action)
+            && field(&self.actor, &entry.actor)
+            && field(&self.ip, &entry.ip)
+            && field(&self.game, &entry.game)
+            && field(&self.instance, &entry.instance)
+            && field(&self.target, &entry.target)
+            && since.is_none_or(|since| time.is_some_and(|time| time >= since))
+            && until.is_none_or(|until| time.is_some_and(|time| time < until))
+    }
+}
+
+/// Append-only log of mutating and security relevant operations, one JSON object per line in
+/// `audit.jsonl` in the data directory.
+pub struct AuditLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl AuditLog {
+    pub fn open(data_path: &Path) -> Result<Self> {
+        let path = data_path.join(AUDIT_FILE_NAME);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(AuditLog {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Appends an entry, failures are logged but never fail the audited operation.
+    pub fn record(&self, actor: &Actor, action: AuditAction, target: AuditTarget) {
+        let entry = AuditEntry {
+            time: Local::now().to_rfc3339(),
+            action,
+            actor: actor.name.clone(),
+            ip: actor.ip.clone(),
+            game: target.game.map(str::to_string),
+            instance: target.instance.map(str::to_string),
+            target: target.target.map(str::to_string),
+        };
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(err) => {
+                error!("Failed to serialize audit entry: {}", err);
+                return;
+            }
+        };
+
+        // One write per entry, so readers see whole lines or a partial last one.
+        let mut file = self.file.lock().unwrap();
+        if let Err(err) = file.write_all(format!("{}\n", line).as_bytes()) {
+            error!("Failed to write {}: {}", AUDIT_FILE_NAME, err);
+        }
+    }
+
+    /// Returns the entries matching `query`, newest first.
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        let parse_time = |time: &Option<String>| -> Result<Option<DateTime<Local>>> {
+            Ok(time
+                .as_deref()
+                .map(DateTime::parse_from_rfc3339)
+                .transpose()?
+                .map(|time| time.with_timezone(&Local)))
+        };
+        let since = parse_time(&query.since)?;
+        let until = parse_time(&query.until)?;
+
+        // Read without the lock so recording never waits for a query. An entry being appended
+        // shows up as a last line without its newline, which is left out.
+        let mut content = fs::read(&self.path)?;
+        let complete = content
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |end| end + 1);
+        content.truncate(complete);
+        let content = String::from_utf8(content)?;
+
+        Ok(content
+            .lines()
+            .rev()
+            .filter_map(|line| {
+                serde_json::from_str::<AuditEntry>(line)
+                    .inspect_err(|err| error!("Invalid audit entry: {}", err))
+                    .ok()
+            })
+            .filter(|entry| query.matches(entry, since, until))
+            .take(query.limit.unwrap_or(AUDIT_QUERY_DEFAULT_LIMIT))
+            .collect())
+    }
+}
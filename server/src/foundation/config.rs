@@ -1,3 +1,4 @@
+use crate::foundation::user::AccessControl;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -39,6 +40,11 @@ pub struct Config {
     pub port: u16,
     pub data_dir: String,
     pub game_def: HashMap<String, GameDef>,
+    /// Hostnames serving a single game or instance at their root, keyed by lowercase hostname without port.
+    pub hosts: HashMap<String, HostDef>,
+    /// Reject every request without a login session, except the login page and share links.
+    pub require_login: bool,
+    pub session_ttl_hours: u32,
 }
 
 impl Default for Config {
@@ -47,6 +53,9 @@ impl Default for Config {
             port: 3000,
             data_dir: String::from("data"),
             game_def: HashMap::new(),
+            hosts: HashMap::new(),
+            require_login: false,
+            session_ttl_hours: 24 * 30,
         }
     }
 }
@@ -86,7 +95,7 @@ impl Config {
                     debug!("Loaded config: {:?}", &config);
 
                     if !&config.get_data_path().exists() {
-                        fs::create_dir_all(&config.get_data_path())?;
+                        fs::create_dir_all(config.get_data_path())?;
                     }
 
                     Ok(config)
@@ -125,6 +134,20 @@ pub struct GameDef {
     pub name: Option<String>,
     pub use_mods: bool,
     pub use_save_sync_mod: bool,
+    /// Let the save-sync-integration mod back up every in-game save automatically.
+    pub auto_sync: bool,
+    /// Minimum seconds between two new automatic saves of one alias, backups in between replace the newest one.
+    pub auto_sync_interval_secs: u64,
+    /// Automatic saves kept per alias, 0 keeps all of them.
+    pub auto_sync_retention: usize,
+    /// Inject a script into the index which backs up the browser storage, for games without mods.
+    pub storage_backup: bool,
+    /// Prefix the browser storage keys of every instance, so instances served from one origin do not share storage.
+    pub storage_isolation: bool,
+    /// Folder polled for dropped index files, mods and archives, relative to the game folder.
+    pub inbox: Option<String>,
+    #[serde(flatten)]
+    pub access: AccessControl,
 }
 
 impl Default for GameDef {
@@ -133,6 +156,13 @@ impl Default for GameDef {
             name: None,
             use_mods: true,
             use_save_sync_mod: true,
+            auto_sync: false,
+            auto_sync_interval_secs: 300,
+            auto_sync_retention: 10,
+            storage_backup: false,
+            storage_isolation: false,
+            inbox: None,
+            access: AccessControl::default(),
         }
     }
 }
@@ -141,4 +171,19 @@ impl GameDef {
     pub fn is_use_save_sync_mod(&self) -> bool {
         self.use_mods && self.use_save_sync_mod
     }
+
+    pub fn is_use_auto_sync(&self) -> bool {
+        self.is_use_save_sync_mod() && self.auto_sync
+    }
+
+    pub fn is_use_storage_backup(&self) -> bool {
+        !self.use_mods && self.storage_backup
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HostDef {
+    pub game: String,
+    /// Serve this instance at `/`, the host lists the game's instances when absent.
+    pub instance: Option<String>,
 }
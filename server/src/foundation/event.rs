@@ -0,0 +1,87 @@
+use crate::foundation::save::{SaveMeta, SaveVisibility, is_save_visible};
+use crate::foundation::user::Identity;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::debug;
+use utoipa::ToSchema;
+
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SaveEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl SaveEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SaveEventKind::Created => "created",
+            SaveEventKind::Updated => "updated",
+            SaveEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+/// A change to a save, scoped to the save namespace it happened in.
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct SaveEvent {
+    pub kind: SaveEventKind,
+    pub game_id: String,
+    pub save_namespace: String,
+    pub save_id: String,
+    /// Owner and visibility of the save, only used to pick the streams the event is sent to.
+    #[serde(skip)]
+    pub owner: Option<String>,
+    #[serde(skip)]
+    pub visibility: SaveVisibility,
+}
+
+impl SaveEvent {
+    /// Whether a stream of `identity` gets the event, which is the case when it may see the save.
+    pub fn is_visible_to(&self, identity: Option<&Identity>) -> bool {
+        is_save_visible(self.owner.as_deref(), self.visibility, identity)
+    }
+}
+
+/// Fans save changes out to every live `save-sync/events` stream.
+pub struct SaveEventHub {
+    sender: broadcast::Sender<SaveEvent>,
+}
+
+impl SaveEventHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        SaveEventHub { sender }
+    }
+
+    /// Publishes a change of a save, `meta` is the metadata the save had before a delete or has
+    /// after any other change.
+    pub fn publish(
+        &self,
+        kind: SaveEventKind,
+        game_id: &str,
+        save_namespace: &str,
+        save_id: &str,
+        meta: &SaveMeta,
+    ) {
+        let event = SaveEvent {
+            kind,
+            game_id: game_id.to_string(),
+            save_namespace: save_namespace.to_string(),
+            save_id: save_id.to_string(),
+            owner: meta.owner.clone(),
+            visibility: meta.visibility,
+        };
+        // Sending only fails when nobody is listening.
+        if self.sender.send(event).is_err() {
+            debug!("No subscriber for save event of {}-{}", game_id, save_id);
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SaveEvent> {
+        self.sender.subscribe()
+    }
+}
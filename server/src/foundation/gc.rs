@@ -0,0 +1,277 @@
+use crate::foundation::registry::{INSTANCE_FORMATS, load_instance_from_file};
+use crate::foundation::structure::{FileInfo, GameInfo};
+use crate::foundation::upload::Staged;
+use crate::util::file::{DiskUsage, list_filename_limit_extension};
+use anyhow::{Result, anyhow};
+use chrono::Local;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{error, warn};
+use utoipa::ToSchema;
+
+/// Collected assets are moved to `archive/{time}/{kind}/` in the game folder, which the registry
+/// does not scan.
+const ARCHIVE_DIR_NAME: &str = "archive";
+
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    Index,
+    Layer,
+    Mod,
+}
+
+impl AssetKind {
+    pub fn get_name(self) -> &'static str {
+        match self {
+            AssetKind::Index => "index",
+            AssetKind::Layer => "layer",
+            AssetKind::Mod => "mod",
+        }
+    }
+}
+
+/// An index, layer or mod of a game.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ToSchema)]
+pub struct AssetRef {
+    pub kind: AssetKind,
+    pub id: String,
+}
+
+impl fmt::Display for AssetRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.kind.get_name(), self.id)
+    }
+}
+
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct UnusedAsset {
+    #[serde(flatten)]
+    pub asset: AssetRef,
+    #[serde(flatten)]
+    pub usage: DiskUsage,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// Assets no instance of a game references and the disk space they take.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct GcReport {
+    pub assets: Vec<UnusedAsset>,
+    pub total: DiskUsage,
+    /// Where the assets were moved to when they were archived.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub archive: Option<PathBuf>,
+}
+
+/// What happens to collected assets.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, ValueEnum, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GcMode {
+    /// Move them to the game's `archive` folder
+    Archive,
+    /// Remove them from disk
+    Delete,
+}
+
+/// The assets named by an instance file, read loosely so a file which failed to load as an
+/// instance still keeps its assets.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct InstanceAssets {
+    index: Option<String>,
+    layers: Vec<String>,
+    mods: Vec<String>,
+}
+
+impl InstanceAssets {
+    fn add_to(self, used: &mut HashSet<(AssetKind, String)>) {
+        used.extend(self.index.map(|id| (AssetKind::Index, id)));
+        used.extend(self.layers.into_iter().map(|id| (AssetKind::Layer, id)));
+        used.extend(self.mods.into_iter().map(|id| (AssetKind::Mod, id)));
+    }
+}
+
+/// Lists the indexes, layers and mods none of the game's instances uses, with their paths.
+/// Besides the loaded instances, every instance file on disk counts, including those which
+/// failed to load.
+fn list_unused_assets(game: &GameInfo) -> Result<Vec<(AssetRef, &Path)>> {
+    let mut used = HashSet::new();
+    for instance in game.instances.values() {
+        InstanceAssets {
+            index: Some(instance.index.clone()),
+            layers: instance.layers.clone(),
+            mods: instance.mods.clone(),
+        }
+        .add_to(&mut used);
+    }
+    let instance_dir = game.get_instance_path();
+    for format in INSTANCE_FORMATS {
+        for (_, file_name) in list_filename_limit_extension(&instance_dir, Some(format))? {
+            match load_instance_from_file::<InstanceAssets>(&instance_dir.join(&file_name), format)
+            {
+                Ok(assets) => assets.add_to(&mut used),
+                Err(err) => warn!("Skipping unreadable instance file '{}': {}", file_name, err),
+            }
+        }
+    }
+
+    let candidates = game
+        .indexes
+        .values()
+        .map(|index| (AssetKind::Index, index as &dyn FileInfo))
+        .chain(
+            game.layers
+                .values()
+                .map(|layer| (AssetKind::Layer, layer as &dyn FileInfo)),
+        )
+        .chain(
+            game.mods
+                .values()
+                .map(|mod_info| (AssetKind::Mod, mod_info as &dyn FileInfo)),
+        );
+    Ok(candidates
+        .filter(|(kind, info)| !used.contains(&(*kind, info.get_id().to_string())))
+        .map(|(kind, info)| {
+            let asset = AssetRef {
+                kind,
+                id: info.get_id().to_string(),
+            };
+            (asset, info.get_path())
+        })
+        .collect())
+}
+
+/// Finds the unused assets of a game along with the disk space they take.
+pub fn find_unused_assets(game: &GameInfo) -> Result<GcReport> {
+    let mut assets = Vec::new();
+    let mut total = DiskUsage::default();
+    for (asset, path) in list_unused_assets(game)? {
+        let usage = DiskUsage::of(path)?;
+        total.add(usage);
+        assets.push(UnusedAsset {
+            asset,
+            usage,
+            path: path.to_path_buf(),
+        });
+    }
+    assets.sort_by(|a, b| a.asset.cmp(&b.asset));
+
+    Ok(GcReport {
+        assets,
+        total,
+        archive: None,
+    })
+}
+
+/// Picks the confirmed assets from a report, which must all be listed in it. Meant to run on a
+/// snapshot of the game before locking it, as measuring the assets walks all their files.
+pub fn select_unused_assets(report: GcReport, confirmed: &[AssetRef]) -> Result<Vec<UnusedAsset>> {
+    let mut assets: Vec<UnusedAsset> = Vec::with_capacity(confirmed.len());
+    for asset in confirmed {
+        if assets.iter().any(|other| &other.asset == asset) {
+            continue;
+        }
+        let unused = report
+            .assets
+            .iter()
+            .find(|unused| &unused.asset == asset)
+            .ok_or_else(|| anyhow!("{} is not an unused asset", asset))?;
+        assets.push(unused.clone());
+    }
+    Ok(assets)
+}
+
+/// Archives or deletes the selected assets and unregisters them. Every asset must still be
+/// unused, so a confirmation given for an older report cannot remove something in use now.
+/// Assets are moved out one by one, deleted ones through the staging directory, and moved back
+/// when one of them fails, so the game is left as it was.
+///
+/// Deleted assets end up in the returned staging directory, drop it once the game is unlocked
+/// again, since removing them can take a while.
+pub fn collect_unused_assets(
+    game: &mut GameInfo,
+    assets: Vec<UnusedAsset>,
+    mode: GcMode,
+) -> Result<(GcReport, Staged)> {
+    let unused = list_unused_assets(game)?;
+    for selected in &assets {
+        if !unused
+            .iter()
+            .any(|(asset, path)| asset == &selected.asset && *path == selected.path)
+        {
+            return Err(anyhow!("{} is not an unused asset", selected.asset));
+        }
+    }
+
+    let archive = match mode {
+        GcMode::Archive => Some(
+            game.path
+                .join(ARCHIVE_DIR_NAME)
+                .join(Local::now().format("%Y-%m-%d+%H-%M-%S").to_string()),
+        ),
+        GcMode::Delete => None,
+    };
+    // Deleted assets are moved here first, it is removed along with them once dropped.
+    let trash = Staged::new(game)?;
+    let target_dir = archive.as_deref().unwrap_or(trash.get_path());
+    let mut moved: Vec<(&Path, PathBuf)> = Vec::with_capacity(assets.len());
+    for unused in &assets {
+        match archive_path(&unused.path, &target_dir.join(unused.asset.kind.get_name())) {
+            Ok(target) => moved.push((&unused.path, target)),
+            Err(err) => {
+                for (path, target) in moved.iter().rev() {
+                    if let Err(err) = fs::rename(target, path) {
+                        error!("Failed to move {:?} back to {:?}: {}", target, path, err);
+                    }
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    let mut total = DiskUsage::default();
+    for unused in &assets {
+        let id = &unused.asset.id;
+        match unused.asset.kind {
+            AssetKind::Index => {
+                game.indexes.remove(id);
+            }
+            AssetKind::Layer => {
+                game.layers.remove(id);
+            }
+            AssetKind::Mod => {
+                game.mods.remove(id);
+            }
+        }
+        total.add(unused.usage);
+    }
+
+    let report = GcReport {
+        assets,
+        total,
+        archive,
+    };
+    Ok((report, trash))
+}
+
+/// Moves a file or directory into `archive_dir` and returns where it ended up.
+fn archive_path(path: &Path, archive_dir: &Path) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{:?} has no file name", path))?;
+    let target = archive_dir.join(file_name);
+    if target.exists() {
+        return Err(anyhow!("{:?} already exists", target));
+    }
+    fs::create_dir_all(archive_dir)?;
+    fs::rename(path, &target)?;
+    Ok(target)
+}
@@ -0,0 +1,287 @@
+use crate::foundation::user::{hash_password, verify_password};
+use crate::util::backoff::Backoff;
+use crate::util::file::{WatchedFile, is_valid_path_segment};
+use crate::util::token::random_token;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use tracing::{error, info};
+
+const HOUSEHOLD_FILE_NAME: &str = "household.json";
+const SELECTION_TOKEN_BYTES: usize = 32;
+/// A picked profile is remembered by its browser for a year.
+pub const SELECTION_MAX_AGE_SECS: u64 = 365 * 24 * 3600;
+/// Browsers remembered per profile, the oldest selection is dropped beyond this.
+const SELECTION_MAX_PER_PROFILE: usize = 32;
+const PIN_LENGTH: std::ops::RangeInclusive<usize> = 4..=8;
+/// Avatar colors of profiles created without one, picked by the profile name.
+const PROFILE_COLORS: [&str; 8] = [
+    "#bb86fc", "#03dac6", "#cf6679", "#ffb74d", "#81c784", "#64b5f6", "#f06292", "#a1887f",
+];
+
+/// A password-less profile of a household member, its name is used as the save alias.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HouseholdProfile {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pin_hash: Option<String>,
+    pub color: String,
+    pub created_at: String,
+}
+
+impl HouseholdProfile {
+    pub fn has_pin(&self) -> bool {
+        self.pin_hash.is_some()
+    }
+
+    pub fn get_initial(&self) -> String {
+        self.name
+            .chars()
+            .next()
+            .map(|initial| initial.to_uppercase().to_string())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct HouseholdData {
+    profiles: HashMap<String, HouseholdProfile>,
+    /// Profiles picked by browsers, keyed by the token in their cookie.
+    selections: HashMap<String, Selection>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Selection {
+    profile: String,
+    selected_at: String,
+}
+
+impl Selection {
+    fn is_expired(&self) -> bool {
+        DateTime::parse_from_rfc3339(&self.selected_at)
+            .map(|selected_at| {
+                Local::now()
+                    .signed_duration_since(selected_at)
+                    .num_seconds()
+                    > SELECTION_MAX_AGE_SECS as i64
+            })
+            .unwrap_or(true)
+    }
+}
+
+/// Household profiles and the browsers which picked them, persisted to `household.json` in the
+/// data directory.
+pub struct HouseholdStore {
+    file: WatchedFile,
+    data: Mutex<HouseholdData>,
+    pin_attempts: Backoff,
+}
+
+impl HouseholdStore {
+    pub fn load(data_path: &Path) -> Result<Self> {
+        let file = WatchedFile::new(data_path.join(HOUSEHOLD_FILE_NAME));
+        let data = read_household(file.get_path())?;
+        info!("Loaded {} household profiles", data.profiles.len());
+
+        Ok(HouseholdStore {
+            file,
+            data: Mutex::new(data),
+            pin_attempts: Backoff::default(),
+        })
+    }
+
+    /// Locks the profiles, reading them again first when a CLI command changed the file.
+    fn lock(&self) -> MutexGuard<'_, HouseholdData> {
+        let mut data = self.data.lock().unwrap();
+        if self.file.is_changed() {
+            match read_household(self.file.get_path()) {
+                Ok(reloaded) => {
+                    info!("Reloaded {} household profiles", reloaded.profiles.len());
+                    *data = reloaded;
+                    self.file.update();
+                }
+                Err(err) => error!("Failed to reload {}: {}", HOUSEHOLD_FILE_NAME, err),
+            }
+        }
+        data
+    }
+
+    pub fn add(&self, name: &str, pin: Option<&str>, color: Option<&str>) -> Result<()> {
+        let name = name.trim();
+        // The name ends up in `{alias}@{timestamp}` save ids.
+        if !is_valid_path_segment(name) || name.contains('@') {
+            return Err(anyhow!("Invalid profile name '{}'", name));
+        }
+        let pin_hash = match pin.filter(|pin| !pin.is_empty()) {
+            Some(pin) if !is_valid_pin(pin) => {
+                return Err(anyhow!("A PIN must have 4 to 8 digits"));
+            }
+            Some(pin) => Some(hash_password(pin)?),
+            None => None,
+        };
+        let color = match color.filter(|color| !color.is_empty()) {
+            Some(color) if !is_valid_color(color) => {
+                return Err(anyhow!("Invalid color '{}', expected #rrggbb", color));
+            }
+            Some(color) => color.to_lowercase(),
+            None => default_color(name).to_string(),
+        };
+
+        let mut data = self.lock();
+        if data.profiles.contains_key(name) {
+            return Err(anyhow!("Profile '{}' already exists", name));
+        }
+        data.profiles.insert(
+            name.to_string(),
+            HouseholdProfile {
+                name: name.to_string(),
+                pin_hash,
+                color,
+                created_at: Local::now().to_rfc3339(),
+            },
+        );
+        self.persist(&data)
+    }
+
+    /// Removes a profile, browsers which picked it are back to no profile.
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let mut data = self.lock();
+        if data.profiles.remove(name).is_none() {
+            return Err(anyhow!("Profile '{}' does not exist", name));
+        }
+        data.selections
+            .retain(|_, selection| selection.profile != name);
+        self.persist(&data)
+    }
+
+    pub fn all(&self) -> Vec<HouseholdProfile> {
+        let mut profiles: Vec<HouseholdProfile> = self.lock().profiles.values().cloned().collect();
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        profiles
+    }
+
+    /// Picks a profile for a browser and returns the token for its cookie. The PIN of profiles
+    /// which have one is checked, with a growing wait after repeated wrong PINs from `client`.
+    /// Checking a PIN is slow, call this from a blocking thread.
+    pub fn select(&self, name: &str, pin: Option<&str>, client: Option<&str>) -> Result<String> {
+        let pin_hash = self
+            .lock()
+            .profiles
+            .get(name)
+            .ok_or_else(|| anyhow!("Profile '{}' does not exist", name))?
+            .pin_hash
+            .clone();
+        if let Some(pin_hash) = pin_hash {
+            self.check_pin(name, client, &pin_hash, pin)?;
+        }
+
+        let mut data = self.lock();
+        if !data.profiles.contains_key(name) {
+            return Err(anyhow!("Profile '{}' does not exist", name));
+        }
+        data.selections
+            .retain(|_, selection| !selection.is_expired());
+        let mut picked: Vec<(String, String)> = data
+            .selections
+            .iter()
+            .filter(|(_, selection)| selection.profile == name)
+            .map(|(token, selection)| (selection.selected_at.clone(), token.clone()))
+            .collect();
+        if picked.len() >= SELECTION_MAX_PER_PROFILE {
+            picked.sort();
+            for (_, token) in &picked[..=picked.len() - SELECTION_MAX_PER_PROFILE] {
+                data.selections.remove(token);
+            }
+        }
+
+        let token = random_token(SELECTION_TOKEN_BYTES);
+        data.selections.insert(
+            token.clone(),
+            Selection {
+                profile: name.to_string(),
+                selected_at: Local::now().to_rfc3339(),
+            },
+        );
+        self.persist(&data)?;
+        Ok(token)
+    }
+
+    fn check_pin(
+        &self,
+        name: &str,
+        client: Option<&str>,
+        pin_hash: &str,
+        pin: Option<&str>,
+    ) -> Result<()> {
+        if let Err(wait) = self.pin_attempts.try_attempt(name, client) {
+            return Err(anyhow!(
+                "Too many wrong PINs, try again in {} seconds",
+                wait.as_secs() + 1
+            ));
+        }
+        if !pin.is_some_and(|pin| verify_password(pin_hash, pin)) {
+            return Err(anyhow!("Wrong PIN"));
+        }
+        self.pin_attempts.reset(name, client);
+        Ok(())
+    }
+
+    /// Whether `name` is a profile protected by a PIN.
+    pub fn has_pin(&self, name: &str) -> bool {
+        self.lock()
+            .profiles
+            .get(name)
+            .is_some_and(|profile| profile.pin_hash.is_some())
+    }
+
+    pub fn get_selected(&self, token: &str) -> Option<HouseholdProfile> {
+        let data = self.lock();
+        data.selections
+            .get(token)
+            .filter(|selection| !selection.is_expired())
+            .and_then(|selection| data.profiles.get(&selection.profile))
+            .cloned()
+    }
+
+    pub fn deselect(&self, token: &str) -> Result<()> {
+        let mut data = self.lock();
+        if data.selections.remove(token).is_some() {
+            self.persist(&data)?;
+        }
+        Ok(())
+    }
+
+    fn persist(&self, data: &HouseholdData) -> Result<()> {
+        self.file
+            .write(&serde_json::to_string_pretty(data)?)
+            .inspect_err(|err| {
+                error!("Failed to write {}: {}", HOUSEHOLD_FILE_NAME, err);
+            })?;
+        Ok(())
+    }
+}
+
+fn read_household(path: &Path) -> Result<HouseholdData> {
+    if !path.exists() {
+        return Ok(HouseholdData::default());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn is_valid_pin(pin: &str) -> bool {
+    PIN_LENGTH.contains(&pin.len()) && pin.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_valid_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn default_color(name: &str) -> &'static str {
+    let index = name.bytes().map(usize::from).sum::<usize>() % PROFILE_COLORS.len();
+    PROFILE_COLORS[index]
+}
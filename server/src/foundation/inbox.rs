@@ -0,0 +1,263 @@
+use crate::foundation::audit::{Actor, AuditAction, AuditTarget};
+use crate::foundation::registry::Registry;
+use crate::foundation::release::{ExtractedRelease, add_release, extract_release};
+use crate::foundation::structure::GameInfo;
+use crate::foundation::upload::{Staged, add_index, add_layer, add_mod, extract_layer};
+use crate::util::AppState;
+use crate::util::archive::{ArchiveFormat, list_archive_files};
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+
+const INBOX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const REJECTED_DIR_NAME: &str = "rejected";
+/// Stripped from archive names to get the id of a mod or layer.
+const ARCHIVE_SUFFIXES: [&str; 4] = [".tar.gz", ".tgz", ".tar", ".zip"];
+
+/// Size and modification time of a dropped file, it is picked up once neither changed for a poll.
+type FileStamp = (u64, Option<SystemTime>);
+
+/// A dropped file unpacked as far as possible before the game is locked to add it.
+enum Prepared {
+    Index(Staged),
+    Mod(Staged),
+    Layer(Staged),
+    Release(ExtractedRelease),
+}
+
+#[derive(Clone, Copy)]
+enum InboxKind {
+    Index,
+    Mod,
+    Layer,
+    Release,
+}
+
+impl InboxKind {
+    fn get_name(self) -> &'static str {
+        match self {
+            InboxKind::Index => "index",
+            InboxKind::Mod => "mod",
+            InboxKind::Layer => "layer",
+            InboxKind::Release => "release",
+        }
+    }
+
+    fn get_action(self) -> AuditAction {
+        match self {
+            InboxKind::Index => AuditAction::IndexUpload,
+            InboxKind::Mod => AuditAction::ModUpload,
+            InboxKind::Layer => AuditAction::LayerUpload,
+            InboxKind::Release => AuditAction::ReleaseImport,
+        }
+    }
+}
+
+pub fn get_inbox_path(game: &GameInfo) -> Option<PathBuf> {
+    game.game_def
+        .inbox
+        .as_ref()
+        .map(|inbox| game.path.join(inbox))
+}
+
+/// Polls the inbox folder of every game which has one configured.
+pub fn spawn_inbox_watcher(state: Arc<AppState>) {
+    let games: Vec<String> = state
+        .registry
+        .all()
+        .into_iter()
+        .filter(|(_, game)| game.game_def.inbox.is_some())
+        .map(|(id, _)| id)
+        .collect();
+    if games.is_empty() {
+        return;
+    }
+    info!("Watching the inbox of {}", games.join(", "));
+
+    tokio::spawn(async move {
+        let mut pending: HashMap<PathBuf, FileStamp> = HashMap::new();
+        let mut interval = tokio::time::interval(INBOX_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let state = state.clone();
+            let games = games.clone();
+            pending = tokio::task::spawn_blocking(move || {
+                for game_id in &games {
+                    poll_inbox(&state, game_id, &mut pending);
+                }
+                pending
+            })
+            .await
+            .unwrap_or_else(|err| {
+                error!("Failed to poll inbox: {}", err);
+                HashMap::new()
+            });
+        }
+    });
+}
+
+fn poll_inbox(state: &AppState, game_id: &str, pending: &mut HashMap<PathBuf, FileStamp>) {
+    let Some(inbox) = state
+        .registry
+        .get(game_id)
+        .and_then(|game| get_inbox_path(&game))
+    else {
+        return;
+    };
+    if let Err(err) = fs::create_dir_all(inbox.join(REJECTED_DIR_NAME)) {
+        error!("Failed to create inbox {:?}: {}", inbox, err);
+        return;
+    }
+    let entries = match fs::read_dir(&inbox) {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Failed to read inbox {:?}: {}", inbox, err);
+            return;
+        }
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        // Hidden files are temporaries of tools still copying, like rsync.
+        if !metadata.is_file() || name.starts_with('.') {
+            continue;
+        }
+        let stamp = (metadata.len(), metadata.modified().ok());
+        if pending.insert(path.clone(), stamp) != Some(stamp) {
+            continue;
+        }
+        pending.remove(&path);
+
+        match ingest_file(state, game_id, &path, &name) {
+            Ok((kind, id)) => {
+                info!(
+                    "Inbox of {} took {} as {} {}",
+                    game_id,
+                    name,
+                    kind.get_name(),
+                    id
+                );
+                state.audit.record(
+                    &Actor::inbox(),
+                    kind.get_action(),
+                    AuditTarget {
+                        game: Some(game_id),
+                        target: Some(&id),
+                        ..Default::default()
+                    },
+                );
+                if let Err(err) = fs::remove_file(&path) {
+                    error!("Failed to remove {:?} from the inbox: {}", path, err);
+                }
+            }
+            Err(err) => {
+                warn!("Inbox of {} rejected {}: {:#}", game_id, name, err);
+                state.audit.record(
+                    &Actor::inbox(),
+                    AuditAction::InboxReject,
+                    AuditTarget {
+                        game: Some(game_id),
+                        target: Some(&name),
+                        ..Default::default()
+                    },
+                );
+                if let Err(err) = reject(&inbox, &path, &name, &err) {
+                    error!("Failed to reject {:?}: {}", path, err);
+                }
+            }
+        }
+    }
+
+    pending.retain(|path, _| path.exists());
+}
+
+/// Adds a dropped file to the game and returns what it was added as. The file is copied, since
+/// the inbox may be on another file system than the game.
+fn ingest_file(
+    state: &AppState,
+    game_id: &str,
+    path: &Path,
+    name: &str,
+) -> Result<(InboxKind, String)> {
+    let game = state
+        .registry
+        .get(game_id)
+        .ok_or_else(|| anyhow!("Game '{}' not found", game_id))?;
+    let staged = Staged::new(&game)?;
+    fs::copy(path, staged.get_path())?;
+    let (kind, id) = classify(staged.get_path(), name)?;
+    let prepared = match kind {
+        InboxKind::Index => Prepared::Index(staged),
+        InboxKind::Mod => Prepared::Mod(staged),
+        InboxKind::Layer => Prepared::Layer(extract_layer(&game, staged)?.0),
+        InboxKind::Release => {
+            Prepared::Release(extract_release(&game, staged.get_path(), Some(name), None)?)
+        }
+    };
+
+    let id = state
+        .registry
+        .update(game_id, |game| match prepared {
+            Prepared::Index(staged) => add_index(game, &id, staged).map(|_| id),
+            Prepared::Mod(staged) => add_mod(game, &id, staged).map(|_| id),
+            Prepared::Layer(extracted) => add_layer(game, &id, extracted).map(|_| id),
+            Prepared::Release(release) => {
+                add_release(game, release, None).map(|imported| imported.version)
+            }
+        })
+        .unwrap_or_else(|| Err(anyhow!("Game '{}' not found", game_id)))?;
+    Ok((kind, id))
+}
+
+/// Tells html files, mods, layers and releases apart. Zips with a `boot.json` at their root are
+/// mods, archives holding an html file are releases and any other archive is a layer.
+fn classify(path: &Path, name: &str) -> Result<(InboxKind, String)> {
+    let lower_name = name.to_ascii_lowercase();
+    if lower_name.ends_with(".html") {
+        return Ok((
+            InboxKind::Index,
+            name[..name.len() - ".html".len()].to_string(),
+        ));
+    }
+    let suffix = ARCHIVE_SUFFIXES
+        .iter()
+        .find(|suffix| lower_name.ends_with(*suffix))
+        .ok_or_else(|| anyhow!("Unsupported file, expected .html, .zip, .tar or .tar.gz"))?;
+    let id = name[..name.len() - suffix.len()].to_string();
+
+    let (format, files) = list_archive_files(path)?;
+    let kind = if format == ArchiveFormat::Zip && files.iter().any(|file| file == "boot.json") {
+        InboxKind::Mod
+    } else if files
+        .iter()
+        .any(|file| file.to_ascii_lowercase().ends_with(".html"))
+    {
+        InboxKind::Release
+    } else {
+        InboxKind::Layer
+    };
+    Ok((kind, id))
+}
+
+/// Moves a file to `rejected/` next to a `{name}.reason.txt` explaining why.
+fn reject(inbox: &Path, path: &Path, name: &str, reason: &anyhow::Error) -> Result<()> {
+    let rejected_dir = inbox.join(REJECTED_DIR_NAME);
+    let target = rejected_dir.join(name);
+    if target.exists() {
+        fs::remove_file(&target)?;
+    }
+    fs::rename(path, &target)?;
+    fs::write(
+        rejected_dir.join(format!("{}.reason.txt", name)),
+        format!("{:#}\n", reason),
+    )?;
+    Ok(())
+}
@@ -1,3 +1,15 @@
+pub(crate) mod api_token;
+pub(crate) mod audit;
 pub(crate) mod config;
+pub(crate) mod event;
+pub(crate) mod gc;
+pub(crate) mod household;
+pub(crate) mod inbox;
 pub(crate) mod registry;
+pub(crate) mod release;
+pub(crate) mod save;
+pub(crate) mod share;
+pub(crate) mod stats;
 pub(crate) mod structure;
+pub(crate) mod upload;
+pub(crate) mod user;
@@ -1,16 +1,22 @@
-use crate::foundation::config::{CONFIG, Config};
+use crate::foundation::config::{CONFIG, Config, GameDef};
 use crate::foundation::structure::{GameInfo, IndexInfo, InstanceInfo, LayerInfo, ModInfo};
-use crate::util::file::{list_dir_name, list_filename_limit_extension};
+use crate::util::file::{is_valid_path_segment, list_dir_name, list_filename_limit_extension};
 use crate::util::vfs::{FileSystemTree, InstanceFS};
 use anyhow::{Result, anyhow};
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
 use tracing::{debug, error, info, warn};
 
+/// Formats instance files can be written in.
+pub const INSTANCE_FORMATS: [&str; 3] = ["json", "toml", "yaml"];
+
 pub fn init_registry() -> Result<GameRegistry> {
     info!("Game registry initialized");
-    let mut registry = GameRegistry::new();
-    load_games(&mut registry)?;
+    let registry = GameRegistry::new();
+    load_games(&registry)?;
     Ok(registry)
 }
 
@@ -20,7 +26,7 @@ fn walk_game_dir(config: &Config) -> Result<()> {
     let defined_game_ids: Vec<String> = config.game_def.keys().cloned().collect();
 
     for defined_game_id in &defined_game_ids {
-        let game_dir = data_dir.join(&defined_game_id);
+        let game_dir = data_dir.join(defined_game_id);
         if !game_dir.exists() {
             fs::create_dir(&game_dir)?;
             info!(
@@ -32,24 +38,23 @@ fn walk_game_dir(config: &Config) -> Result<()> {
 
     let actual_dirs = fs::read_dir(&data_dir)?
         .filter_map(Result::ok)
-        .filter(|e| e.path().is_dir())
-        .into_iter();
+        .filter(|e| e.path().is_dir());
 
     for actual_dir in actual_dirs {
-        if let Some(dir_name) = actual_dir.file_name().to_str() {
-            if !defined_game_ids.contains(&dir_name.to_string()) {
-                warn!(
-                    "Directory '{}' exists but is not defined in the config",
-                    dir_name
-                );
-            }
+        if let Some(dir_name) = actual_dir.file_name().to_str()
+            && !defined_game_ids.contains(&dir_name.to_string())
+        {
+            warn!(
+                "Directory '{}' exists but is not defined in the config",
+                dir_name
+            );
         }
     }
 
     Ok(())
 }
 
-fn load_games(registry: &mut GameRegistry) -> Result<()> {
+fn load_games(registry: &GameRegistry) -> Result<()> {
     if let Some(config) = CONFIG.get() {
         if config.game_def.is_empty() {
             warn!(
@@ -62,35 +67,57 @@ fn load_games(registry: &mut GameRegistry) -> Result<()> {
             walk_game_dir(config)?;
 
             for (id, def) in &config.game_def {
-                info!("Loading game: '{}'", id);
+                registry.add(load_game(id, data_dir.join(id), def.clone())?);
+            }
+        }
+    } else {
+        return Err(anyhow!("Config not initialized"));
+    }
+    Ok(())
+}
 
-                let that_path = data_dir.join(id);
-                let mut that_game = GameInfo::of(id, that_path, def.clone());
+fn load_game(id: &str, path: PathBuf, def: GameDef) -> Result<GameInfo> {
+    info!("Loading game: '{}'", id);
 
-                debug!("Loading index for game: {}", id);
-                load_index(&mut that_game)?;
+    let use_mods = def.use_mods;
+    let mut that_game = GameInfo::of(id, path, def);
 
-                if def.use_mods {
-                    debug!("Loading mod for game: {}", id);
-                    load_mod(&mut that_game)?;
-                }
+    debug!("Loading index for game: {}", id);
+    load_index(&mut that_game)?;
 
-                debug!("Loading layer for game: {}", id);
-                load_layer(&mut that_game)?;
-                debug!("Loading instance for game: {}", id);
-                load_instance(&mut that_game)?;
+    if use_mods {
+        debug!("Loading mod for game: {}", id);
+        load_mod(&mut that_game)?;
+    }
 
-                if !that_game.get_save_path().exists() {
-                    fs::create_dir(&that_game.get_save_path())?;
-                }
+    debug!("Loading layer for game: {}", id);
+    load_layer(&mut that_game)?;
+    debug!("Loading instance for game: {}", id);
+    load_instance(&mut that_game)?;
 
-                registry.add(that_game);
-            }
-        }
-    } else {
-        return Err(anyhow!("Config not initialized"));
+    if !that_game.get_save_path().exists() {
+        fs::create_dir(that_game.get_save_path())?;
     }
-    Ok(())
+
+    Ok(that_game)
+}
+
+/// Loads a single game, for commands run without the server.
+pub fn load_configured_game(config: &Config, id: &str) -> Result<GameInfo> {
+    let def = config
+        .game_def
+        .get(id)
+        .ok_or_else(|| anyhow!("Game '{}' is not defined in the config", id))?;
+    load_game(id, config.get_data_path().join(id), def.clone())
+}
+
+/// Rescans the indexes, mods, layers and instances of a game from its directory, for files
+/// changed outside the server. Returns `None` for unknown games.
+pub fn reload_game(registry: &GameRegistry, id: &str) -> Option<Result<()>> {
+    registry.update(id, |game| {
+        *game = load_game(id, game.path.clone(), game.game_def.clone())?;
+        Ok(())
+    })
 }
 
 fn load_index(game: &mut GameInfo) -> Result<()> {
@@ -164,10 +191,9 @@ fn load_instance(game: &mut GameInfo) -> Result<()> {
         fs::create_dir(&instance_dir)?;
     }
 
-    let formats = vec!["json", "toml", "yaml"];
     let mut loaded_instances = 0;
 
-    for format in formats {
+    for format in INSTANCE_FORMATS {
         let files = list_filename_limit_extension(&instance_dir, Some(format))?;
         if !files.is_empty() {
             debug!(
@@ -179,8 +205,9 @@ fn load_instance(game: &mut GameInfo) -> Result<()> {
 
             for (_, file_name) in files {
                 let path_to_file = instance_dir.join(&file_name);
-                match load_instance_from_file(&path_to_file, format) {
-                    Ok(instance) => {
+                match load_instance_from_file::<InstanceInfo>(&path_to_file, format) {
+                    Ok(mut instance) => {
+                        instance.file = Some(path_to_file);
                         let id = instance.id.clone();
                         if process_loaded_instance(game, instance)? {
                             loaded_instances += 1;
@@ -199,6 +226,15 @@ fn load_instance(game: &mut GameInfo) -> Result<()> {
         }
     }
 
+    for (alias, id) in &game.instance_aliases {
+        if game.instances.contains_key(alias) {
+            warn!(
+                "Alias '{}' of instance {} is shadowed by an instance with the same id",
+                alias, id
+            );
+        }
+    }
+
     if loaded_instances == 0 {
         warn!("No instance files found for '{}'", &game.id);
     } else {
@@ -208,7 +244,11 @@ fn load_instance(game: &mut GameInfo) -> Result<()> {
     Ok(())
 }
 
-fn load_instance_from_file(path: &std::path::Path, format: &str) -> Result<InstanceInfo> {
+/// Parses an instance file written in `format`, into [`InstanceInfo`] or any looser shape.
+pub fn load_instance_from_file<T: DeserializeOwned>(
+    path: &std::path::Path,
+    format: &str,
+) -> Result<T> {
     let content = fs::read_to_string(path)?;
 
     match format {
@@ -224,6 +264,11 @@ fn process_loaded_instance(game: &mut GameInfo, mut instance: InstanceInfo) -> R
         return Ok(false);
     }
 
+    if let Err(err) = check_save_namespace(&instance) {
+        error!("{}", err);
+        return Err(err);
+    }
+
     let mut layer_fs_collection = Vec::with_capacity(instance.layers.len());
 
     for layer_id in &instance.layers {
@@ -262,43 +307,192 @@ fn process_loaded_instance(game: &mut GameInfo, mut instance: InstanceInfo) -> R
     );
 
     instance.fs = Some(instance_fs);
-    game.instances.insert(instance.id.clone(), instance);
+
+    for alias in &instance.aliases {
+        if let Some(existing) = game.instance_aliases.get(alias) {
+            warn!(
+                "Alias '{}' of instance {} already points to {}, skipping.",
+                alias, &instance.id, existing
+            );
+            continue;
+        }
+        game.instance_aliases
+            .insert(alias.clone(), instance.id.clone());
+    }
+
+    game.instances
+        .insert(instance.id.clone(), Arc::new(instance));
 
     Ok(true)
 }
 
+fn check_save_namespace(instance: &InstanceInfo) -> Result<()> {
+    if !is_valid_path_segment(instance.get_save_namespace()) {
+        return Err(anyhow!(
+            "Save namespace '{}' of instance {} is not a valid directory name",
+            instance.get_save_namespace(),
+            &instance.id
+        ));
+    }
+    Ok(())
+}
+
+/// Checks an instance about to be written against the game, `replacing` is the id of the instance
+/// it replaces.
+pub fn validate_instance(
+    game: &GameInfo,
+    instance: &InstanceInfo,
+    replacing: Option<&str>,
+) -> Result<()> {
+    if !is_valid_path_segment(&instance.id) {
+        return Err(anyhow!("Invalid instance id '{}'", &instance.id));
+    }
+    check_save_namespace(instance)?;
+
+    if !game.indexes.contains_key(&instance.index) {
+        return Err(anyhow!("Index '{}' not found", &instance.index));
+    }
+    if let Some(layer_id) = instance
+        .layers
+        .iter()
+        .find(|layer_id| !game.layers.contains_key(*layer_id))
+    {
+        return Err(anyhow!("Layer '{}' not found", layer_id));
+    }
+    if let Some(mod_id) = instance
+        .mods
+        .iter()
+        .find(|mod_id| !game.mods.contains_key(*mod_id))
+    {
+        return Err(anyhow!("Mod '{}' not found", mod_id));
+    }
+
+    // Ids and aliases of other instances stay reserved to them.
+    let is_other = |id: &str| replacing != Some(id);
+    for name in std::iter::once(&instance.id).chain(&instance.aliases) {
+        if (game.instances.contains_key(name) && is_other(name))
+            || game
+                .instance_aliases
+                .get(name)
+                .is_some_and(|id| is_other(id))
+        {
+            return Err(anyhow!("Instance id or alias '{}' already in use", name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes an instance to `instance/{id}.{format}` and builds its file system, replacing the
+/// instance with the id `replacing` and the file it was loaded from.
+pub fn write_instance(
+    game: &mut GameInfo,
+    mut instance: InstanceInfo,
+    format: &str,
+    replacing: Option<&str>,
+) -> Result<()> {
+    let content = match format {
+        "json" => serde_json::to_string_pretty(&instance)?,
+        "toml" => toml::to_string_pretty(&instance)?,
+        "yaml" => serde_yaml::to_string(&instance)?,
+        _ => return Err(anyhow!("Unsupported format: {}", format)),
+    };
+    let path = game
+        .get_instance_path()
+        .join(format!("{}.{}", &instance.id, format));
+
+    let previous = replacing.and_then(|id| remove_loaded_instance(game, id));
+    instance.file = Some(path.clone());
+    if !process_loaded_instance(game, instance)? {
+        return Err(anyhow!("Instance already exists"));
+    }
+
+    let temp_path = path.with_extension(format!("{}.tmp", format));
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, &path)?;
+
+    if let Some(previous_file) = previous.and_then(|previous| previous.file.clone())
+        && previous_file != path
+    {
+        fs::remove_file(&previous_file)?;
+    }
+
+    Ok(())
+}
+
+/// Removes an instance and its file, its saves are kept.
+pub fn delete_instance(game: &mut GameInfo, id: &str) -> Result<()> {
+    let instance =
+        remove_loaded_instance(game, id).ok_or_else(|| anyhow!("Instance '{}' not found", id))?;
+    if let Some(file) = &instance.file {
+        fs::remove_file(file)?;
+    }
+    Ok(())
+}
+
+fn remove_loaded_instance(game: &mut GameInfo, id: &str) -> Option<Arc<InstanceInfo>> {
+    let instance = game.instances.remove(id)?;
+    game.instance_aliases.retain(|_, target| target != id);
+    Some(instance)
+}
+
 pub struct GameRegistry {
-    registry: HashMap<String, GameInfo>,
+    registry: RwLock<HashMap<String, Arc<GameInfo>>>,
+    /// Serializes updates, readers are only blocked while the updated game is swapped in.
+    update_lock: Mutex<()>,
 }
 
 impl GameRegistry {
     pub fn new() -> Self {
         GameRegistry {
-            registry: HashMap::new(),
+            registry: RwLock::new(HashMap::new()),
+            update_lock: Mutex::new(()),
         }
     }
+
+    /// Applies `change` to a copy of a game and swaps the copy in once it succeeded, requests
+    /// already holding the game keep using the old one. Returns `None` for unknown games.
+    pub fn update<R, E>(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut GameInfo) -> Result<R, E>,
+    ) -> Option<Result<R, E>> {
+        let _update = self.update_lock.lock().unwrap();
+        let game = self.get(id)?;
+        let mut game = GameInfo::clone(&game);
+        let result = change(&mut game);
+        if result.is_ok() {
+            self.add(game);
+        }
+        Some(result)
+    }
 }
 
 pub trait Registry<T> {
-    fn add(&mut self, item: T);
-    fn get(&self, id: &str) -> Option<&T>;
+    fn add(&self, item: T);
+    fn get(&self, id: &str) -> Option<Arc<T>>;
 
-    fn all(&self) -> Vec<(String, &T)>;
+    fn all(&self) -> Vec<(String, Arc<T>)>;
 }
 
 impl Registry<GameInfo> for GameRegistry {
-    fn add(&mut self, item: GameInfo) {
-        self.registry.insert(item.id.clone(), item);
+    fn add(&self, item: GameInfo) {
+        self.registry
+            .write()
+            .unwrap()
+            .insert(item.id.clone(), Arc::new(item));
     }
 
-    fn get(&self, id: &str) -> Option<&GameInfo> {
-        self.registry.get(id)
+    fn get(&self, id: &str) -> Option<Arc<GameInfo>> {
+        self.registry.read().unwrap().get(id).cloned()
     }
 
-    fn all(&self) -> Vec<(String, &GameInfo)> {
+    fn all(&self) -> Vec<(String, Arc<GameInfo>)> {
         self.registry
+            .read()
+            .unwrap()
             .iter()
-            .map(|(id, game)| (id.clone(), game))
+            .map(|(id, game)| (id.clone(), game.clone()))
             .collect()
     }
 }
@@ -0,0 +1,281 @@
+use crate::foundation::registry::{validate_instance, write_instance};
+use crate::foundation::structure::{GameInfo, IndexInfo, InstanceInfo, LayerInfo};
+use crate::foundation::upload::{LAYER_EXTRACTED_MAX_SIZE, Staged, is_valid_content_id};
+use crate::foundation::user::AccessControl;
+use crate::util::archive::extract_archive;
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+/// How far after `StartConfig` the version is looked for.
+const START_CONFIG_SEARCH_LENGTH: usize = 4096;
+
+/// What an imported release was turned into.
+#[derive(Serialize, ToSchema)]
+pub struct ReleaseImport {
+    /// Also the id of the imported index.
+    pub version: String,
+    /// `None` for releases without an `img` folder.
+    pub layer: Option<String>,
+    pub instance: Option<String>,
+}
+
+/// A release archive unpacked into the staging directory, ready for [`add_release`].
+pub struct ExtractedRelease {
+    version: String,
+    html_path: PathBuf,
+    /// Removes whatever was not placed.
+    _extracted: Staged,
+}
+
+/// Imports a release archive holding the game's html file and its `img` folder as
+/// `index/{version}.html` and `layer/{version}-img`, optionally with an instance using both.
+/// The version is taken from `version`, the html file or `archive_name`, in that order.
+pub fn import_release(
+    game: &mut GameInfo,
+    archive: &Path,
+    archive_name: Option<&str>,
+    version: Option<&str>,
+    instance_id: Option<&str>,
+) -> Result<ReleaseImport> {
+    let release = extract_release(game, archive, archive_name, version)?;
+    add_release(game, release, instance_id)
+}
+
+/// The slow half of [`import_release`], which does not change the game and can run before it is
+/// locked for [`add_release`].
+pub fn extract_release(
+    game: &GameInfo,
+    archive: &Path,
+    archive_name: Option<&str>,
+    version: Option<&str>,
+) -> Result<ExtractedRelease> {
+    let extracted = Staged::new(game)?;
+    extract_archive(archive, extracted.get_path(), LAYER_EXTRACTED_MAX_SIZE)?;
+    let html_path = find_entry_html(extracted.get_path())?;
+
+    let version = match version {
+        Some(version) => version.to_string(),
+        None => fs::read_to_string(&html_path)
+            .ok()
+            .and_then(|html| version_from_html(&html))
+            .or_else(|| archive_name.and_then(version_from_name))
+            .ok_or_else(|| anyhow!("Cannot infer the release version, pass it explicitly"))?,
+    };
+    if !is_valid_content_id(&version) {
+        return Err(anyhow!("Invalid release version '{}'", version));
+    }
+    Ok(ExtractedRelease {
+        version,
+        html_path,
+        _extracted: extracted,
+    })
+}
+
+/// Places and registers an extracted release, optionally with an instance using it.
+pub fn add_release(
+    game: &mut GameInfo,
+    release: ExtractedRelease,
+    instance_id: Option<&str>,
+) -> Result<ReleaseImport> {
+    let ExtractedRelease {
+        version,
+        html_path,
+        _extracted,
+    } = release;
+    if game.indexes.contains_key(&version) {
+        return Err(anyhow!("index '{}' already exists", version));
+    }
+    let img_path = html_path.with_file_name("img");
+    let layer_id = img_path.is_dir().then(|| format!("{}-img", version));
+    if let Some(layer_id) = &layer_id
+        && game.layers.contains_key(layer_id)
+    {
+        return Err(anyhow!("layer '{}' already exists", layer_id));
+    }
+
+    let index_file_name = format!("{}.html", version);
+    game.indexes.insert(
+        version.clone(),
+        IndexInfo::of(&version, &index_file_name, &game.get_index_path()),
+    );
+    if let Some(layer_id) = &layer_id {
+        game.layers.insert(
+            layer_id.clone(),
+            LayerInfo::of(layer_id, &game.get_layer_path()),
+        );
+    }
+    let instance = instance_id.map(|instance_id| InstanceInfo {
+        id: instance_id.to_string(),
+        index: version.clone(),
+        name: None,
+        mods: Vec::new(),
+        layers: layer_id.iter().cloned().collect(),
+        save_namespace: None,
+        aliases: Vec::new(),
+        access: AccessControl::default(),
+        fs: None,
+        file: None,
+    });
+    if let Some(instance) = &instance {
+        if game.instances.contains_key(&instance.id) {
+            return Err(anyhow!("Instance '{}' already exists", instance.id));
+        }
+        validate_instance(game, instance, None)?;
+    }
+
+    let index_path = game.get_index_path().join(&index_file_name);
+    place_path(&html_path, &index_path)?;
+    if let Some(layer_id) = &layer_id
+        && let Err(err) = place_img_layer(game, &img_path, layer_id)
+    {
+        let _ = fs::remove_file(&index_path);
+        return Err(err);
+    }
+    let instance_id = instance.as_ref().map(|instance| instance.id.clone());
+    if let Some(instance) = instance
+        && let Err(err) = write_instance(game, instance, "json", None)
+    {
+        let _ = fs::remove_file(&index_path);
+        if let Some(layer_id) = &layer_id {
+            let _ = fs::remove_dir_all(game.get_layer_path().join(layer_id));
+        }
+        return Err(err);
+    }
+
+    Ok(ReleaseImport {
+        version,
+        layer: layer_id,
+        instance: instance_id,
+    })
+}
+
+/// The game loads `img/...` relative to its html, so the folder is kept inside the layer.
+fn place_img_layer(game: &GameInfo, img_path: &Path, layer_id: &str) -> Result<()> {
+    let layer = Staged::new(game)?;
+    fs::create_dir(layer.get_path())?;
+    place_path(img_path, &layer.get_path().join("img"))?;
+    layer.place(&game.get_layer_path().join(layer_id))
+}
+
+fn place_path(source: &Path, target: &Path) -> Result<()> {
+    if target.exists() {
+        return Err(anyhow!("{:?} already exists", target));
+    }
+    fs::rename(source, target)?;
+    Ok(())
+}
+
+/// The html file closest to the root of the archive, which must be the only one at its depth.
+fn find_entry_html(root: &Path) -> Result<PathBuf> {
+    let mut level = vec![root.to_path_buf()];
+    while !level.is_empty() {
+        let mut html_files = Vec::new();
+        let mut next_level = Vec::new();
+        for dir in &level {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    next_level.push(path);
+                } else if path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("html"))
+                {
+                    html_files.push(path);
+                }
+            }
+        }
+
+        match html_files.len() {
+            0 => level = next_level,
+            1 => return Ok(html_files.remove(0)),
+            _ => {
+                let names: Vec<String> = html_files
+                    .iter()
+                    .filter_map(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().to_string())
+                    .collect();
+                return Err(anyhow!(
+                    "Several html files found in the release: {}",
+                    names.join(", ")
+                ));
+            }
+        }
+    }
+    Err(anyhow!("No html file found in the release"))
+}
+
+/// Degrees of Lewdity declares its version in `window.StartConfig = { version: "0.5.2.8", ... }`.
+fn version_from_html(html: &str) -> Option<String> {
+    let config = &html[html.find("StartConfig")?..];
+    let config = config
+        .char_indices()
+        .nth(START_CONFIG_SEARCH_LENGTH)
+        .map_or(config, |(end, _)| &config[..end]);
+
+    config.match_indices("version").find_map(|(index, _)| {
+        let rest = config[index + "version".len()..]
+            .trim_start_matches(['"', '\''])
+            .trim_start()
+            .strip_prefix(':')?
+            .trim_start();
+        let quote = rest
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '"' | '\'' | '`'))?;
+        let value = &rest[1..rest[1..].find(quote)? + 1];
+        is_version(value).then(|| value.to_string())
+    })
+}
+
+/// Finds a version like `0.5.2.8` in a file name such as `Degrees-of-Lewdity-0.5.2.8.zip`.
+fn version_from_name(name: &str) -> Option<String> {
+    let file_name = Path::new(name).file_name()?.to_str()?;
+    file_name
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .map(|part| part.trim_matches('.'))
+        .find(|part| is_version(part))
+        .map(str::to_string)
+}
+
+fn is_version(value: &str) -> bool {
+    value.starts_with(|c: char| c.is_ascii_digit())
+        && value.contains('.')
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_is_read_from_start_config() {
+        let html = r#"<script>window.StartConfig = { debug: false, version: "0.5.2.8", enableImages: true };</script>"#;
+        assert_eq!(version_from_html(html).as_deref(), Some("0.5.2.8"));
+
+        let quoted = r#"StartConfig = {"enableLinkNumberify": true, "version": '0.4.7.3-fix'}"#;
+        assert_eq!(version_from_html(quoted).as_deref(), Some("0.4.7.3-fix"));
+    }
+
+    #[test]
+    fn version_needs_start_config_and_a_version_value() {
+        assert_eq!(version_from_html(r#"var version = "0.5.2.8";"#), None);
+        assert_eq!(
+            version_from_html(r#"StartConfig = { versionCheck: "1.0", version: "beta" }"#),
+            None
+        );
+    }
+
+    #[test]
+    fn version_is_read_from_file_names() {
+        assert_eq!(
+            version_from_name("dl/Degrees-of-Lewdity-0.5.2.8.zip").as_deref(),
+            Some("0.5.2.8")
+        );
+        assert_eq!(version_from_name("Degrees-of-Lewdity.zip"), None);
+    }
+}
@@ -0,0 +1,1087 @@
+use crate::foundation::user::Identity;
+use crate::util::file::{content_hash, encode_path_segment, is_valid_path_segment};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{debug, error, warn};
+use utoipa::ToSchema;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+const SAVE_FILE_EXTENSION: &str = "save";
+const SAVE_META_EXTENSION: &str = "meta.json";
+const SAVE_ARCHIVE_MANIFEST: &str = "manifest.json";
+const SAVE_TIMESTAMP_FORMAT: &str = "%Y-%m-%d+%H-%M-%S";
+const AUTO_SAVE_DIR: &str = "auto";
+const PROFILE_DIR: &str = "profile";
+const OWNER_DIR: &str = "user";
+const PROFILE_VALUE_EXTENSION: &str = "value";
+const STORAGE_BACKUP_FILE_NAME: &str = "storage-backup.json";
+pub const SAVE_ARCHIVE_MAX_SIZE: usize = 256 * 1024 * 1024;
+pub const SAVE_UPLOAD_MAX_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Deserialize, ToSchema)]
+pub struct SaveCode {
+    pub code: String,
+    alias: String,
+}
+
+impl SaveCode {
+    pub fn get_alias_no_empty(&self) -> String {
+        alias_or_anonymous(&self.alias)
+    }
+
+    /// Replaces the alias sent by the client, used to enforce the picked household profile.
+    pub fn set_alias(&mut self, alias: String) {
+        self.alias = alias;
+    }
+}
+
+/// Saves collected by the mod's auto sync since its last flush, oldest first.
+#[derive(Deserialize, ToSchema)]
+pub struct AutoSaveBatch {
+    #[serde(default)]
+    alias: String,
+    pub saves: Vec<AutoSaveItem>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AutoSaveItem {
+    pub code: String,
+    /// The SugarCube save type which triggered the backup, e.g. `autosave` or `slot`.
+    #[serde(default)]
+    pub kind: Option<String>,
+}
+
+impl AutoSaveBatch {
+    pub fn get_alias_no_empty(&self) -> String {
+        alias_or_anonymous(&self.alias)
+    }
+}
+
+fn alias_or_anonymous(alias: &str) -> String {
+    if alias.is_empty() {
+        String::from("anonymous")
+    } else {
+        alias.to_string()
+    }
+}
+
+pub fn iter_save_list(save_dir: &PathBuf) -> Vec<String> {
+    if !save_dir.exists() {
+        fs::create_dir(save_dir).unwrap_or_else(|err| {
+            error!("Failed to create save directory: {}", err);
+        });
+    }
+    fs::read_dir(save_dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| {
+                    entry.path().extension().and_then(|ext| ext.to_str())
+                        == Some(SAVE_FILE_EXTENSION)
+                })
+                .filter_map(|entry| {
+                    entry
+                        .path()
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .map(String::from)
+                })
+                .collect()
+        })
+        .unwrap_or_else(|err| {
+            error!("Failed to read save directory: {}", err);
+            vec![]
+        })
+}
+
+pub fn get_save_content(save_dir: &PathBuf, save_id: &str) -> Option<String> {
+    if !save_dir.exists() {
+        fs::create_dir(save_dir).unwrap_or_else(|err| {
+            error!("Failed to create save directory: {}", err);
+        });
+    }
+    match fs::read_to_string(save_dir.join(assemble_save_name(save_id))) {
+        Ok(file) => Some(file),
+        Err(err) => {
+            error!("Failed to read save file ({}) : {}", save_id, err);
+            None
+        }
+    }
+}
+
+/// Whether a save exists, without reading its content.
+pub fn save_exists(save_dir: &Path, save_id: &str) -> bool {
+    save_dir.join(assemble_save_name(save_id)).is_file()
+}
+
+/// Deletes a save and its metadata, returns whether the save existed.
+pub fn del_save_content(save_dir: &PathBuf, save_id: &str) -> bool {
+    if !save_dir.exists() {
+        fs::create_dir(save_dir).unwrap_or_else(|err| {
+            error!("Failed to create save directory: {}", err);
+        });
+    }
+    let file_path = save_dir.join(assemble_save_name(save_id));
+    if file_path.exists() {
+        if let Err(err) = fs::remove_file(&file_path) {
+            error!("Failed to delete save file ({}) : {}", save_id, err);
+            return false;
+        }
+        let meta_path = save_dir.join(assemble_meta_name(save_id));
+        if meta_path.exists()
+            && let Err(err) = fs::remove_file(&meta_path)
+        {
+            error!("Failed to delete save meta ({}) : {}", save_id, err);
+        }
+        true
+    } else {
+        warn!("Save file not found for deletion: {}", file_path.display());
+        false
+    }
+}
+
+/// Writes an uploaded save. If the same alias of the same owner already has a save with identical
+/// content, that save is touched instead of writing a copy.
+pub fn write_save_content(
+    save_dir: &PathBuf,
+    game_id: &str,
+    instance_id: &str,
+    code: SaveCode,
+    owner: Option<&str>,
+) -> Result<SaveWriteResult> {
+    let alias = code.get_alias_no_empty();
+    let hash = content_hash(code.code.as_bytes());
+
+    if !save_dir.exists() {
+        fs::create_dir(save_dir).unwrap_or_else(|err| {
+            error!("Failed to create save directory: {}", err);
+        });
+    }
+
+    if let Some(save_id) = find_duplicate_save(save_dir, &alias, &hash, owner) {
+        debug!("Save content of '{}' already stored as {}", alias, save_id);
+        touch_save(save_dir, &save_id)?;
+        return Ok(SaveWriteResult {
+            id: save_id,
+            duplicate: true,
+        });
+    }
+
+    let (save_id, mut file) = create_save_file(save_dir, &alias)?;
+    debug!("Writing save {} ({game_id}-{instance_id})", save_id);
+    file.write_all(code.code.as_bytes())?;
+
+    write_save_meta(
+        save_dir,
+        &save_id,
+        &SaveMeta {
+            hash: Some(hash),
+            owner: owner.map(str::to_string),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(SaveWriteResult {
+        id: save_id,
+        duplicate: false,
+    })
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SaveWriteResult {
+    pub id: String,
+    pub duplicate: bool,
+}
+
+/// Metadata stored next to a save file as `{save_id}.meta.json`.
+#[derive(Serialize, Deserialize, Default, Debug, ToSchema)]
+#[serde(default)]
+pub struct SaveMeta {
+    pub hash: Option<String>,
+    /// Last time an identical upload was folded into this save.
+    pub touched_at: Option<String>,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    /// Pinned saves cannot be deleted and are never removed by retention rules.
+    pub pinned: bool,
+    /// The logged in user who stored the save, saves from anonymous requests have no owner.
+    pub owner: Option<String>,
+    pub visibility: SaveVisibility,
+}
+
+/// Who besides its owner and admins may see an owned save, saves without owner are seen by everyone.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SaveVisibility {
+    #[default]
+    Private,
+    /// Everyone who can access the instance may see and load the save.
+    Instance,
+}
+
+/// Changes to the user editable part of [`SaveMeta`], absent fields are left untouched.
+#[derive(Deserialize, Default, ToSchema)]
+#[serde(default)]
+pub struct SaveMetaPatch {
+    pub note: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub pinned: Option<bool>,
+    pub visibility: Option<SaveVisibility>,
+}
+
+impl SaveMeta {
+    pub fn apply(&mut self, patch: SaveMetaPatch) {
+        if let Some(note) = patch.note {
+            let note = note.trim();
+            self.note = if note.is_empty() {
+                None
+            } else {
+                Some(note.to_string())
+            };
+        }
+        if let Some(tags) = patch.tags {
+            self.tags = Vec::with_capacity(tags.len());
+            for tag in tags {
+                let tag = tag.trim();
+                if !tag.is_empty() && !self.tags.iter().any(|existing| existing == tag) {
+                    self.tags.push(tag.to_string());
+                }
+            }
+        }
+        if let Some(pinned) = patch.pinned {
+            self.pinned = pinned;
+        }
+        if let Some(visibility) = patch.visibility {
+            self.visibility = visibility;
+        }
+    }
+
+    /// Whether `identity` may change or delete the save.
+    pub fn is_owned_by(&self, identity: Option<&Identity>) -> bool {
+        is_save_owner(self.owner.as_deref(), identity)
+    }
+
+    /// Whether `identity` may see and load the save.
+    pub fn is_visible_to(&self, identity: Option<&Identity>) -> bool {
+        is_save_visible(self.owner.as_deref(), self.visibility, identity)
+    }
+}
+
+fn is_save_owner(owner: Option<&str>, identity: Option<&Identity>) -> bool {
+    match owner {
+        None => true,
+        Some(owner) => identity.is_some_and(|identity| identity.admin || identity.name == owner),
+    }
+}
+
+/// Whether `identity` may see a save with `owner` and `visibility`, see [`SaveMeta::is_visible_to`].
+pub fn is_save_visible(
+    owner: Option<&str>,
+    visibility: SaveVisibility,
+    identity: Option<&Identity>,
+) -> bool {
+    visibility == SaveVisibility::Instance || is_save_owner(owner, identity)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SaveEntry {
+    pub id: String,
+    #[serde(flatten)]
+    pub meta: SaveMeta,
+}
+
+pub fn list_save_entries(save_dir: &PathBuf) -> Vec<SaveEntry> {
+    iter_save_list(save_dir)
+        .into_iter()
+        .map(|save_id| SaveEntry {
+            meta: read_save_meta(save_dir, &save_id),
+            id: save_id,
+        })
+        .collect()
+}
+
+pub fn read_save_meta(save_dir: &Path, save_id: &str) -> SaveMeta {
+    let meta_path = save_dir.join(assemble_meta_name(save_id));
+    if !meta_path.exists() {
+        return SaveMeta::default();
+    }
+    fs::read_to_string(&meta_path)
+        .map_err(|err| anyhow!(err))
+        .and_then(|content| serde_json::from_str(&content).map_err(|err| anyhow!(err)))
+        .unwrap_or_else(|err| {
+            error!("Failed to read save meta ({}) : {}", save_id, err);
+            SaveMeta::default()
+        })
+}
+
+pub fn write_save_meta(save_dir: &Path, save_id: &str, meta: &SaveMeta) -> Result<()> {
+    fs::write(
+        save_dir.join(assemble_meta_name(save_id)),
+        serde_json::to_string_pretty(meta)?,
+    )?;
+    Ok(())
+}
+
+/// Returns the content hash of a save, computing and storing it for saves without one.
+pub fn get_save_hash(save_dir: &Path, save_id: &str) -> Result<String> {
+    let mut meta = read_save_meta(save_dir, save_id);
+    if let Some(hash) = &meta.hash {
+        return Ok(hash.clone());
+    }
+
+    let hash = content_hash(&fs::read(save_dir.join(assemble_save_name(save_id)))?);
+    meta.hash = Some(hash.clone());
+    write_save_meta(save_dir, save_id, &meta)?;
+
+    Ok(hash)
+}
+
+/// The alias part of an `{alias}@{timestamp}` save id.
+pub fn get_save_alias(save_id: &str) -> &str {
+    save_id.rsplit_once('@').map_or(save_id, |(alias, _)| alias)
+}
+
+/// The creation time encoded in an `{alias}@{timestamp}` save id.
+fn get_save_time(save_id: &str) -> Option<NaiveDateTime> {
+    get_save_order(save_id).map(|(time, _)| time)
+}
+
+/// Sorts save ids by creation, saves made unique by [`free_save_id`] follow the plain id of the
+/// same second.
+fn get_save_order(save_id: &str) -> Option<(NaiveDateTime, u32)> {
+    let (_, timestamp) = save_id.rsplit_once('@')?;
+    if let Ok(time) = NaiveDateTime::parse_from_str(timestamp, SAVE_TIMESTAMP_FORMAT) {
+        return Some((time, 0));
+    }
+    let (timestamp, n) = timestamp.rsplit_once('-')?;
+    let time = NaiveDateTime::parse_from_str(timestamp, SAVE_TIMESTAMP_FORMAT).ok()?;
+    Some((time, n.parse().ok()?))
+}
+
+/// Automatic backups live apart from manual uploads, in `auto/` below the save directory.
+pub fn get_auto_save_dir(save_dir: &Path) -> PathBuf {
+    save_dir.join(AUTO_SAVE_DIR)
+}
+
+/// Profile values and the storage snapshot are private to the logged in user who wrote them, each
+/// user keeps them in `user/{name}/` below the save directory. Anonymous requests use the save
+/// directory itself.
+pub fn get_owner_dir(save_dir: &Path, owner: Option<&str>) -> PathBuf {
+    match owner {
+        Some(owner) => save_dir.join(OWNER_DIR).join(encode_path_segment(owner)),
+        None => save_dir.to_path_buf(),
+    }
+}
+
+/// Profile storage keeps settings blobs per alias, in `profile/{alias}/` below the owner directory.
+pub fn get_profile_dir(owner_dir: &Path, alias: &str) -> PathBuf {
+    owner_dir.join(PROFILE_DIR).join(alias_or_anonymous(alias))
+}
+
+/// Aliases that have a profile directory below `owner_dir`.
+pub fn list_profile_aliases(owner_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(owner_dir.join(PROFILE_DIR)) else {
+        return Vec::new();
+    };
+    let mut aliases: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect();
+    aliases.sort();
+    aliases
+}
+
+pub fn list_profile_keys(profile_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(profile_dir) else {
+        return Vec::new();
+    };
+    let mut keys: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(&format!(".{}", PROFILE_VALUE_EXTENSION)))
+                .map(str::to_string)
+        })
+        .collect();
+    keys.sort();
+    keys
+}
+
+pub fn get_profile_value(profile_dir: &Path, key: &str) -> Option<String> {
+    fs::read_to_string(profile_dir.join(assemble_profile_name(key))).ok()
+}
+
+pub fn write_profile_value(profile_dir: &Path, key: &str, value: &str) -> Result<()> {
+    fs::create_dir_all(profile_dir)?;
+    fs::write(profile_dir.join(assemble_profile_name(key)), value)?;
+    Ok(())
+}
+
+/// Deletes a profile value, returns whether the value existed.
+pub fn del_profile_value(profile_dir: &Path, key: &str) -> bool {
+    let path = profile_dir.join(assemble_profile_name(key));
+    if !path.exists() {
+        return false;
+    }
+    match fs::remove_file(&path) {
+        Ok(_) => true,
+        Err(err) => {
+            error!("Failed to delete profile value ({}) : {}", key, err);
+            false
+        }
+    }
+}
+
+/// The browser storage snapshot of a game without mods, one per save namespace and owner.
+pub fn get_storage_backup(owner_dir: &Path) -> Option<String> {
+    fs::read_to_string(owner_dir.join(STORAGE_BACKUP_FILE_NAME)).ok()
+}
+
+/// Replaces the storage snapshot, written to a temporary file first so a failed upload keeps the old one.
+pub fn write_storage_backup(owner_dir: &Path, snapshot: &str) -> Result<()> {
+    fs::create_dir_all(owner_dir)?;
+    let tmp_path = owner_dir.join(format!("{}.tmp", STORAGE_BACKUP_FILE_NAME));
+    fs::write(&tmp_path, snapshot)?;
+    fs::rename(&tmp_path, owner_dir.join(STORAGE_BACKUP_FILE_NAME))?;
+    Ok(())
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoSaveOutcome {
+    /// A new automatic save was written.
+    Created,
+    /// The newest automatic save was still inside the throttle window and got replaced.
+    Coalesced,
+    /// The newest automatic save already has this content.
+    Duplicate,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AutoSaveResult {
+    pub id: String,
+    pub outcome: AutoSaveOutcome,
+    /// Saves removed by the retention limit.
+    pub pruned: Vec<String>,
+}
+
+/// Stores an automatic backup for `alias`. Only one new save is created per `interval`,
+/// later backups inside that window replace it, and at most `retention` automatic saves are
+/// kept per alias.
+pub fn write_auto_save(
+    save_dir: &Path,
+    alias: &str,
+    code: &str,
+    tags: Vec<String>,
+    interval: Duration,
+    retention: usize,
+    owner: Option<&str>,
+) -> Result<AutoSaveResult> {
+    let auto_dir = get_auto_save_dir(save_dir);
+    fs::create_dir_all(&auto_dir)?;
+
+    let hash = content_hash(code.as_bytes());
+    let latest = iter_save_list(&auto_dir)
+        .into_iter()
+        .filter(|save_id| get_save_alias(save_id) == alias)
+        .filter(|save_id| is_owned_save(&auto_dir, save_id, owner))
+        .max_by_key(|save_id| get_save_order(save_id));
+
+    if let Some(latest) = &latest
+        && get_save_hash(&auto_dir, latest).is_ok_and(|existing| existing == hash)
+    {
+        touch_save(&auto_dir, latest)?;
+        return Ok(AutoSaveResult {
+            id: latest.clone(),
+            outcome: AutoSaveOutcome::Duplicate,
+            pruned: Vec::new(),
+        });
+    }
+
+    let now = Local::now().naive_local();
+    let (save_id, outcome) = match latest {
+        Some(latest) if get_save_time(&latest).is_some_and(|time| now - time < interval) => {
+            (latest, AutoSaveOutcome::Coalesced)
+        }
+        _ => (
+            create_save_file(&auto_dir, alias)?.0,
+            AutoSaveOutcome::Created,
+        ),
+    };
+
+    let mut meta = read_save_meta(&auto_dir, &save_id);
+    fs::write(auto_dir.join(assemble_save_name(&save_id)), code)?;
+    meta.hash = Some(hash);
+    meta.tags = tags;
+    meta.owner = owner.map(str::to_string);
+    write_save_meta(&auto_dir, &save_id, &meta)?;
+
+    let pruned = if outcome == AutoSaveOutcome::Created {
+        prune_auto_saves(&auto_dir, alias, retention, owner)
+    } else {
+        Vec::new()
+    };
+
+    Ok(AutoSaveResult {
+        id: save_id,
+        outcome,
+        pruned,
+    })
+}
+
+/// Removes the oldest automatic saves `owner` stored for `alias` beyond `retention`, saves worth
+/// keeping are copied out with the keep endpoint. A `retention` of 0 keeps every save.
+fn prune_auto_saves(
+    auto_dir: &PathBuf,
+    alias: &str,
+    retention: usize,
+    owner: Option<&str>,
+) -> Vec<String> {
+    if retention == 0 {
+        return Vec::new();
+    }
+    let mut saves: Vec<String> = iter_save_list(auto_dir)
+        .into_iter()
+        .filter(|save_id| get_save_alias(save_id) == alias)
+        .filter(|save_id| is_owned_save(auto_dir, save_id, owner))
+        .collect();
+    if saves.len() <= retention {
+        return Vec::new();
+    }
+
+    saves.sort_by_key(|save_id| get_save_order(save_id));
+    let excess = saves.len() - retention;
+    saves
+        .into_iter()
+        .take(excess)
+        .filter(|save_id| del_save_content(auto_dir, save_id))
+        .collect()
+}
+
+fn find_duplicate_save(
+    save_dir: &PathBuf,
+    alias: &str,
+    hash: &str,
+    owner: Option<&str>,
+) -> Option<String> {
+    iter_save_list(save_dir)
+        .into_iter()
+        .filter(|save_id| get_save_alias(save_id) == alias)
+        .filter(|save_id| is_owned_save(save_dir, save_id, owner))
+        .find(|save_id| match get_save_hash(save_dir, save_id) {
+            Ok(existing) => existing == hash,
+            Err(err) => {
+                error!("Failed to hash save file ({}) : {}", save_id, err);
+                false
+            }
+        })
+}
+
+/// Whether the save was stored by `owner`, deduplication never folds saves of different owners.
+fn is_owned_save(save_dir: &Path, save_id: &str, owner: Option<&str>) -> bool {
+    read_save_meta(save_dir, save_id).owner.as_deref() == owner
+}
+
+fn touch_save(save_dir: &Path, save_id: &str) -> Result<()> {
+    fs::File::options()
+        .write(true)
+        .open(save_dir.join(assemble_save_name(save_id)))?
+        .set_modified(SystemTime::now())?;
+
+    let mut meta = read_save_meta(save_dir, save_id);
+    meta.touched_at = Some(Local::now().to_rfc3339());
+    write_save_meta(save_dir, save_id, &meta)
+}
+
+/// Copies a save into another save directory, unless identical content is already stored there.
+/// The copy keeps note and tags but belongs to `owner`.
+pub fn copy_save(
+    source_dir: &Path,
+    save_id: &str,
+    target_dir: &PathBuf,
+    owner: Option<&str>,
+) -> Result<SaveWriteResult> {
+    let content = fs::read(source_dir.join(assemble_save_name(save_id)))?;
+    let hash = content_hash(&content);
+
+    if !target_dir.exists() {
+        fs::create_dir(target_dir)?;
+    }
+    if let Some(existing) = iter_save_list(target_dir)
+        .into_iter()
+        .filter(|existing| is_owned_save(target_dir, existing, owner))
+        .find(|existing| get_save_hash(target_dir, existing).is_ok_and(|h| h == hash))
+    {
+        return Ok(SaveWriteResult {
+            id: existing,
+            duplicate: true,
+        });
+    }
+
+    let source_meta = read_save_meta(source_dir, save_id);
+    let target_id = free_save_id(target_dir, save_id)?;
+    fs::write(target_dir.join(assemble_save_name(&target_id)), &content)?;
+    write_save_meta(
+        target_dir,
+        &target_id,
+        &SaveMeta {
+            hash: Some(hash),
+            note: source_meta.note,
+            tags: source_meta.tags,
+            owner: owner.map(str::to_string),
+            ..Default::default()
+        },
+    )?;
+
+    Ok(SaveWriteResult {
+        id: target_id,
+        duplicate: false,
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveArchiveManifest {
+    pub game_id: String,
+    pub instance_id: String,
+    pub save_namespace: String,
+    pub exported_at: String,
+    pub saves: Vec<SaveArchiveEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveArchiveEntry {
+    id: String,
+    hash: String,
+    size: u64,
+    modified: Option<String>,
+    #[serde(default)]
+    note: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    pinned: bool,
+}
+
+#[derive(Serialize, Default, ToSchema)]
+pub struct SaveImportReport {
+    pub imported: Vec<String>,
+    /// Saves whose content already exists, and profile values as `profile/{alias}/{key}` whose key
+    /// is already set.
+    pub duplicates: Vec<String>,
+    pub rejected: Vec<String>,
+    /// Profile values restored, as `{alias}/{key}`.
+    pub profile_values: Vec<String>,
+}
+
+/// Packs the saves visible to `identity` and the profile values together with a manifest into a
+/// zip archive written to `file`, so the archive is never held in memory as a whole. Aliases
+/// `is_unlocked` turns down are left out.
+pub fn build_save_archive(
+    save_dir: &PathBuf,
+    mut manifest: SaveArchiveManifest,
+    identity: Option<&Identity>,
+    is_unlocked: impl Fn(&str) -> bool,
+    file: fs::File,
+) -> Result<()> {
+    let mut writer = ZipWriter::new(file);
+    let options = SimpleFileOptions::default();
+
+    for save_id in iter_save_list(save_dir) {
+        let meta = read_save_meta(save_dir, &save_id);
+        if !meta.is_visible_to(identity) || !is_unlocked(get_save_alias(&save_id)) {
+            continue;
+        }
+
+        let path = save_dir.join(assemble_save_name(&save_id));
+        let content = fs::read(&path)?;
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .map(|time| DateTime::<Local>::from(time).to_rfc3339())
+            .ok();
+
+        writer.start_file(assemble_save_name(&save_id), options)?;
+        writer.write_all(&content)?;
+
+        manifest.saves.push(SaveArchiveEntry {
+            hash: content_hash(&content),
+            size: content.len() as u64,
+            id: save_id,
+            modified,
+            note: meta.note,
+            tags: meta.tags,
+            pinned: meta.pinned,
+        });
+    }
+
+    let owner_dir = get_owner_dir(save_dir, identity.map(|identity| identity.name.as_str()));
+    for alias in list_profile_aliases(&owner_dir) {
+        if !is_unlocked(&alias) {
+            continue;
+        }
+        let profile_dir = get_profile_dir(&owner_dir, &alias);
+        for key in list_profile_keys(&profile_dir) {
+            let Some(value) = get_profile_value(&profile_dir, &key) else {
+                continue;
+            };
+            writer.start_file(assemble_profile_entry(&alias, &key), options)?;
+            writer.write_all(value.as_bytes())?;
+        }
+    }
+
+    writer.start_file(SAVE_ARCHIVE_MANIFEST, options)?;
+    writer.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// Imports the saves and profile values of an archive for `owner`, those of aliases `is_unlocked`
+/// turns down are rejected.
+pub fn import_save_archive(
+    save_dir: &PathBuf,
+    archive: &[u8],
+    owner: Option<&str>,
+    is_unlocked: impl Fn(&str) -> bool,
+) -> Result<SaveImportReport> {
+    let mut archive = ZipArchive::new(Cursor::new(archive))?;
+
+    let manifest = match archive.by_name(SAVE_ARCHIVE_MANIFEST) {
+        Ok(mut file) => {
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            Some(serde_json::from_str::<SaveArchiveManifest>(&content)?)
+        }
+        Err(_) => None,
+    };
+    let mut manifest_entries: HashMap<String, SaveArchiveEntry> = manifest
+        .map(|manifest| {
+            manifest
+                .saves
+                .into_iter()
+                .map(|entry| (entry.id.clone(), entry))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if !save_dir.exists() {
+        fs::create_dir(save_dir)?;
+    }
+    let mut existing_hashes: HashMap<String, String> = HashMap::new();
+    for save_id in iter_save_list(save_dir) {
+        if is_owned_save(save_dir, &save_id, owner) {
+            existing_hashes.insert(get_save_hash(save_dir, &save_id)?, save_id);
+        }
+    }
+
+    let mut report = SaveImportReport::default();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if !file.is_file() {
+            continue;
+        }
+        let Some(entry_path) = file.enclosed_name() else {
+            report.rejected.push(file.name().to_string());
+            continue;
+        };
+        if let Some((alias, key)) = parse_profile_entry(&entry_path) {
+            let profile_dir = get_profile_dir(&get_owner_dir(save_dir, owner), &alias);
+            let name = format!("{}/{}", alias, key);
+            if !is_unlocked(&alias) {
+                report.rejected.push(name);
+                continue;
+            }
+            let Some(value) =
+                read_archive_entry(&mut file)?.and_then(|content| String::from_utf8(content).ok())
+            else {
+                report.rejected.push(name);
+                continue;
+            };
+            // Values set since the export are newer than the archive, they are left alone.
+            if get_profile_value(&profile_dir, &key).is_some() {
+                report.duplicates.push(format!("{}/{}", PROFILE_DIR, name));
+                continue;
+            }
+            write_profile_value(&profile_dir, &key, &value)?;
+            report.profile_values.push(name);
+            continue;
+        }
+        if entry_path.extension().and_then(|ext| ext.to_str()) != Some(SAVE_FILE_EXTENSION) {
+            continue;
+        }
+        let Some(save_id) = entry_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .filter(|stem| is_valid_path_segment(stem))
+            .map(String::from)
+        else {
+            report.rejected.push(file.name().to_string());
+            continue;
+        };
+        if !is_unlocked(get_save_alias(&save_id)) {
+            report.rejected.push(save_id);
+            continue;
+        }
+
+        let Some(content) = read_archive_entry(&mut file)? else {
+            warn!("Save '{}' in archive exceeds the save size limit", save_id);
+            report.rejected.push(save_id);
+            continue;
+        };
+        let hash = content_hash(&content);
+
+        let manifest_entry = manifest_entries.remove(&save_id);
+        if manifest_entry
+            .as_ref()
+            .is_some_and(|entry| entry.hash != hash)
+        {
+            warn!("Save '{}' does not match its manifest hash", save_id);
+            report.rejected.push(save_id);
+            continue;
+        }
+        if existing_hashes.contains_key(&hash) {
+            report.duplicates.push(save_id);
+            continue;
+        }
+
+        let target_id = free_save_id(save_dir, &save_id)?;
+        fs::write(save_dir.join(assemble_save_name(&target_id)), &content)?;
+        write_save_meta(
+            save_dir,
+            &target_id,
+            &SaveMeta {
+                hash: Some(hash.clone()),
+                note: manifest_entry.as_ref().and_then(|entry| entry.note.clone()),
+                tags: manifest_entry
+                    .as_ref()
+                    .map(|entry| entry.tags.clone())
+                    .unwrap_or_default(),
+                pinned: manifest_entry.is_some_and(|entry| entry.pinned),
+                owner: owner.map(str::to_string),
+                ..Default::default()
+            },
+        )?;
+        existing_hashes.insert(hash, target_id.clone());
+        report.imported.push(target_id);
+    }
+
+    Ok(report)
+}
+
+/// Reads an archive entry, `None` when it is larger than a save upload may be. The declared size
+/// comes from the archive itself and is not trusted.
+fn read_archive_entry(file: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut content = Vec::new();
+    file.take(SAVE_UPLOAD_MAX_SIZE as u64 + 1)
+        .read_to_end(&mut content)?;
+    if content.len() > SAVE_UPLOAD_MAX_SIZE {
+        return Ok(None);
+    }
+    Ok(Some(content))
+}
+
+/// Splits an archive entry `profile/{alias}/{key}.value` into alias and key.
+fn parse_profile_entry(entry_path: &Path) -> Option<(String, String)> {
+    let mut components = entry_path.iter().map(|component| component.to_str());
+    let (Some(Some(PROFILE_DIR)), Some(Some(alias)), Some(Some(name)), None) = (
+        components.next(),
+        components.next(),
+        components.next(),
+        components.next(),
+    ) else {
+        return None;
+    };
+    let key = name.strip_suffix(&format!(".{}", PROFILE_VALUE_EXTENSION))?;
+    (is_valid_path_segment(alias) && is_valid_path_segment(key))
+        .then(|| (alias.to_string(), key.to_string()))
+}
+
+/// Returns `save_id`, or the first `save_id-N` which is not taken yet.
+fn free_save_id(save_dir: &Path, save_id: &str) -> Result<String> {
+    if !save_dir.join(assemble_save_name(save_id)).exists() {
+        return Ok(save_id.to_string());
+    }
+    (1..1000)
+        .map(|n| format!("{}-{}", save_id, n))
+        .find(|candidate| !save_dir.join(assemble_save_name(candidate)).exists())
+        .ok_or_else(|| anyhow!("No free save id for '{}'", save_id))
+}
+
+/// Creates the empty file of a new save of `alias`. Ids have a precision of one second, so another
+/// owner may store the same alias at the same time, the id of the later one gets a `-N` suffix.
+fn create_save_file(save_dir: &Path, alias: &str) -> Result<(String, fs::File)> {
+    let base_id = assemble_save_id(alias);
+    loop {
+        let save_id = free_save_id(save_dir, &base_id)?;
+        match fs::File::create_new(save_dir.join(assemble_save_name(&save_id))) {
+            Ok(file) => return Ok((save_id, file)),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+fn assemble_save_id(alias: &str) -> String {
+    format!("{}@{}", alias, Local::now().format(SAVE_TIMESTAMP_FORMAT))
+}
+
+fn assemble_save_name(save_id: &str) -> String {
+    format!("{}.{}", save_id, SAVE_FILE_EXTENSION)
+}
+
+fn assemble_meta_name(save_id: &str) -> String {
+    format!("{}.{}", save_id, SAVE_META_EXTENSION)
+}
+
+fn assemble_profile_name(key: &str) -> String {
+    format!("{}.{}", key, PROFILE_VALUE_EXTENSION)
+}
+
+fn assemble_profile_entry(alias: &str, key: &str) -> String {
+    format!("{}/{}/{}", PROFILE_DIR, alias, assemble_profile_name(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::file::TempDir;
+
+    fn save_code(alias: &str, code: &str) -> SaveCode {
+        SaveCode {
+            code: code.to_string(),
+            alias: alias.to_string(),
+        }
+    }
+
+    fn user(name: &str, admin: bool) -> Identity {
+        Identity {
+            name: name.to_string(),
+            admin,
+            groups: Vec::new(),
+            scope: None,
+        }
+    }
+
+    fn write_auto(
+        save_dir: &Path,
+        code: &str,
+        retention: usize,
+        owner: Option<&str>,
+    ) -> AutoSaveResult {
+        write_auto_save(
+            save_dir,
+            "mia",
+            code,
+            Vec::new(),
+            Duration::zero(),
+            retention,
+            owner,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn duplicate_uploads_are_folded_per_owner() {
+        let dir = TempDir::new();
+        let first =
+            write_save_content(&dir.0, "g", "i", save_code("mia", "A"), Some("alice")).unwrap();
+        let again =
+            write_save_content(&dir.0, "g", "i", save_code("mia", "A"), Some("alice")).unwrap();
+        let other =
+            write_save_content(&dir.0, "g", "i", save_code("mia", "A"), Some("bob")).unwrap();
+
+        assert!(!first.duplicate);
+        assert!(again.duplicate);
+        assert_eq!(again.id, first.id);
+        assert!(!other.duplicate);
+        assert_ne!(other.id, first.id);
+        assert_eq!(
+            read_save_meta(&dir.0, &other.id).owner.as_deref(),
+            Some("bob")
+        );
+    }
+
+    #[test]
+    fn uploads_in_the_same_second_never_overwrite() {
+        let dir = TempDir::new();
+        let first = write_save_content(&dir.0, "g", "i", save_code("mia", "A"), None).unwrap();
+        let second = write_save_content(&dir.0, "g", "i", save_code("mia", "B"), None).unwrap();
+
+        assert_ne!(first.id, second.id);
+        assert_eq!(get_save_content(&dir.0, &first.id).as_deref(), Some("A"));
+        assert_eq!(get_save_content(&dir.0, &second.id).as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn auto_saves_are_created_under_free_ids() {
+        let dir = TempDir::new();
+        let first = write_auto(&dir.0, "A", 10, Some("alice"));
+        let second = write_auto(&dir.0, "B", 10, Some("bob"));
+
+        let auto_dir = get_auto_save_dir(&dir.0);
+        assert_eq!(first.outcome, AutoSaveOutcome::Created);
+        assert_eq!(second.outcome, AutoSaveOutcome::Created);
+        assert_ne!(first.id, second.id);
+        assert_eq!(get_save_content(&auto_dir, &first.id).as_deref(), Some("A"));
+        assert_eq!(
+            get_save_content(&auto_dir, &second.id).as_deref(),
+            Some("B")
+        );
+    }
+
+    #[test]
+    fn auto_save_retention_only_prunes_the_owner() {
+        let dir = TempDir::new();
+        let bob = write_auto(&dir.0, "B", 2, Some("bob"));
+        let oldest = write_auto(&dir.0, "A1", 2, Some("alice"));
+        write_auto(&dir.0, "A2", 2, Some("alice"));
+        let newest = write_auto(&dir.0, "A3", 2, Some("alice"));
+
+        assert_eq!(newest.pruned, vec![oldest.id]);
+        let auto_dir = get_auto_save_dir(&dir.0);
+        assert!(save_exists(&auto_dir, &bob.id));
+        assert_eq!(iter_save_list(&auto_dir).len(), 3);
+    }
+
+    #[test]
+    fn auto_save_retention_of_zero_keeps_everything() {
+        let dir = TempDir::new();
+        for code in ["A", "B", "C"] {
+            assert!(write_auto(&dir.0, code, 0, None).pruned.is_empty());
+        }
+        assert_eq!(iter_save_list(&get_auto_save_dir(&dir.0)).len(), 3);
+    }
+
+    #[test]
+    fn save_visibility() {
+        let anonymous = SaveMeta::default();
+        let private = SaveMeta {
+            owner: Some(String::from("alice")),
+            ..Default::default()
+        };
+        let shared = SaveMeta {
+            owner: Some(String::from("alice")),
+            visibility: SaveVisibility::Instance,
+            ..Default::default()
+        };
+
+        assert!(anonymous.is_visible_to(None));
+        assert!(anonymous.is_visible_to(Some(&user("bob", false))));
+        assert!(!private.is_visible_to(None));
+        assert!(!private.is_visible_to(Some(&user("bob", false))));
+        assert!(private.is_visible_to(Some(&user("alice", false))));
+        assert!(private.is_visible_to(Some(&user("root", true))));
+        assert!(shared.is_visible_to(None));
+        assert!(!shared.is_owned_by(Some(&user("bob", false))));
+    }
+}
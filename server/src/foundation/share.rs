@@ -0,0 +1,112 @@
+use crate::util::token::random_token;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{error, info};
+
+const SHARE_FILE_NAME: &str = "shares.json";
+const SHARE_TOKEN_BYTES: usize = 24;
+/// Share links live at most a year.
+pub const SHARE_MAX_EXPIRES_IN_HOURS: u32 = 24 * 366;
+
+/// A read-only link to a single save, addressed by an unguessable token.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveShare {
+    pub game_id: String,
+    pub instance_id: String,
+    pub save_namespace: String,
+    pub save_id: String,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+}
+
+impl SaveShare {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .as_ref()
+            .and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())
+            .is_some_and(|expires_at| expires_at < Local::now())
+    }
+}
+
+/// Share tokens, persisted to `shares.json` in the data directory.
+pub struct ShareStore {
+    path: PathBuf,
+    shares: Mutex<HashMap<String, SaveShare>>,
+}
+
+impl ShareStore {
+    pub fn load(data_path: &Path) -> Result<Self> {
+        let path = data_path.join(SHARE_FILE_NAME);
+        let mut shares: HashMap<String, SaveShare> = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            HashMap::new()
+        };
+
+        let total = shares.len();
+        shares.retain(|_, share| !share.is_expired());
+        info!(
+            "Loaded {} save shares, dropped {} expired",
+            shares.len(),
+            total - shares.len()
+        );
+
+        Ok(ShareStore {
+            path,
+            shares: Mutex::new(shares),
+        })
+    }
+
+    pub fn create(
+        &self,
+        game_id: &str,
+        instance_id: &str,
+        save_namespace: &str,
+        save_id: &str,
+        expires_in: Option<Duration>,
+    ) -> Result<(String, SaveShare)> {
+        let token = random_token(SHARE_TOKEN_BYTES);
+
+        let now = Local::now();
+        let expires_at = match expires_in {
+            Some(duration) => Some(
+                now.checked_add_signed(duration)
+                    .ok_or_else(|| anyhow!("Share expiry is out of range"))?
+                    .to_rfc3339(),
+            ),
+            None => None,
+        };
+        let share = SaveShare {
+            game_id: game_id.to_string(),
+            instance_id: instance_id.to_string(),
+            save_namespace: save_namespace.to_string(),
+            save_id: save_id.to_string(),
+            created_at: now.to_rfc3339(),
+            expires_at,
+        };
+
+        let mut shares = self.shares.lock().unwrap();
+        shares.retain(|_, share| !share.is_expired());
+        shares.insert(token.clone(), share.clone());
+        self.persist(&shares)?;
+
+        Ok((token, share))
+    }
+
+    /// Returns the share for `token`, expired shares are returned as well so callers can tell them apart.
+    pub fn get(&self, token: &str) -> Option<SaveShare> {
+        self.shares.lock().unwrap().get(token).cloned()
+    }
+
+    fn persist(&self, shares: &HashMap<String, SaveShare>) -> Result<()> {
+        fs::write(&self.path, serde_json::to_string_pretty(shares)?).inspect_err(|err| {
+            error!("Failed to write {}: {}", SHARE_FILE_NAME, err);
+        })?;
+        Ok(())
+    }
+}
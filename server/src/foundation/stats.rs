@@ -0,0 +1,156 @@
+use crate::foundation::structure::{FileInfo, GameInfo};
+use crate::util::file::{DiskUsage, list_dir_name};
+use crate::util::vfs::FileSystemTree;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct AssetStats {
+    pub id: String,
+    #[serde(flatten)]
+    pub usage: DiskUsage,
+}
+
+/// The files an instance serves: its index, mods and the files of its merged layers.
+#[derive(Serialize, ToSchema)]
+pub struct InstanceStats {
+    pub id: String,
+    #[serde(flatten)]
+    pub usage: DiskUsage,
+    /// Bytes of files no other instance serves.
+    pub unique_bytes: u64,
+    /// Bytes of files other instances serve as well.
+    pub shared_bytes: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SaveStats {
+    pub namespace: String,
+    /// Instances saving to the namespace.
+    pub instances: Vec<String>,
+    #[serde(flatten)]
+    pub usage: DiskUsage,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GameStats {
+    /// Everything in the game folder.
+    pub total: DiskUsage,
+    pub indexes: Vec<AssetStats>,
+    pub layers: Vec<AssetStats>,
+    pub mods: Vec<AssetStats>,
+    pub instances: Vec<InstanceStats>,
+    pub saves: Vec<SaveStats>,
+}
+
+/// Measures the content of a game, this walks every file so it is kept off the async workers.
+pub fn get_game_stats(game: &GameInfo) -> Result<GameStats> {
+    Ok(GameStats {
+        total: DiskUsage::of(&game.path)?,
+        indexes: get_asset_stats(game.indexes.values())?,
+        layers: get_asset_stats(game.layers.values())?,
+        mods: get_asset_stats(game.mods.values())?,
+        instances: get_instance_stats(game),
+        saves: get_save_stats(game)?,
+    })
+}
+
+fn get_asset_stats<'a, T: FileInfo + 'a>(
+    assets: impl Iterator<Item = &'a T>,
+) -> Result<Vec<AssetStats>> {
+    let mut stats = assets
+        .map(|asset| {
+            Ok(AssetStats {
+                id: asset.get_id().to_string(),
+                usage: DiskUsage::of(asset.get_path())?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    stats.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(stats)
+}
+
+fn get_instance_stats(game: &GameInfo) -> Vec<InstanceStats> {
+    let served: Vec<(&str, Vec<&Path>)> = game
+        .instances
+        .values()
+        .map(|instance| {
+            let mut paths = instance
+                .get_fs()
+                .map(|instance_fs| instance_fs.get_real_paths())
+                .unwrap_or_default();
+            paths.extend(
+                game.indexes
+                    .get(&instance.index)
+                    .map(|index| index.get_path()),
+            );
+            paths.extend(
+                instance
+                    .mods
+                    .iter()
+                    .filter_map(|mod_id| game.mods.get(mod_id))
+                    .map(|mod_info| mod_info.get_path()),
+            );
+            (instance.id.as_str(), paths)
+        })
+        .collect();
+
+    // Layers are shared between instances, each file is measured once.
+    let mut sizes: HashMap<&Path, (u64, usize)> = HashMap::new();
+    for path in served.iter().flat_map(|(_, paths)| paths) {
+        sizes
+            .entry(path)
+            .or_insert_with(|| (fs::metadata(path).map_or(0, |metadata| metadata.len()), 0))
+            .1 += 1;
+    }
+
+    let mut stats: Vec<InstanceStats> = served
+        .iter()
+        .map(|(id, paths)| {
+            let mut stats = InstanceStats {
+                id: id.to_string(),
+                usage: DiskUsage::default(),
+                unique_bytes: 0,
+                shared_bytes: 0,
+            };
+            for path in paths {
+                let (bytes, users) = sizes[path];
+                stats.usage.add(DiskUsage { bytes, files: 1 });
+                if users == 1 {
+                    stats.unique_bytes += bytes;
+                } else {
+                    stats.shared_bytes += bytes;
+                }
+            }
+            stats
+        })
+        .collect();
+    stats.sort_by(|a, b| a.id.cmp(&b.id));
+    stats
+}
+
+fn get_save_stats(game: &GameInfo) -> Result<Vec<SaveStats>> {
+    let mut stats = list_dir_name(&game.get_save_path())?
+        .into_iter()
+        .map(|namespace| {
+            let mut instances: Vec<String> = game
+                .instances
+                .values()
+                .filter(|instance| instance.get_save_namespace() == namespace)
+                .map(|instance| instance.id.clone())
+                .collect();
+            instances.sort();
+            Ok(SaveStats {
+                usage: DiskUsage::of(&game.get_save_path_append(&namespace))?,
+                namespace,
+                instances,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    stats.sort_by(|a, b| a.namespace.cmp(&b.namespace));
+    Ok(stats)
+}
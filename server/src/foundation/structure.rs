@@ -1,18 +1,24 @@
 use crate::foundation::config::GameDef;
+use crate::foundation::user::{AccessControl, Identity};
 use crate::util::vfs::{InstanceFS, LayerFS};
 use anyhow::{Context, Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use utoipa::ToSchema;
 
+/// Cloning is cheap, instances and layer file systems are shared between clones.
+#[derive(Clone)]
 pub struct GameInfo {
     pub id: String,
     pub path: PathBuf,
-    pub instances: HashMap<String, InstanceInfo>,
+    pub instances: HashMap<String, Arc<InstanceInfo>>,
     pub indexes: HashMap<String, IndexInfo>,
     pub layers: HashMap<String, LayerInfo>,
     pub mods: HashMap<String, ModInfo>,
+    pub instance_aliases: HashMap<String, String>,
     pub game_def: GameDef,
 }
 
@@ -25,6 +31,7 @@ impl GameInfo {
             indexes: HashMap::new(),
             layers: HashMap::new(),
             mods: HashMap::new(),
+            instance_aliases: HashMap::new(),
             game_def: def_copy,
         }
     }
@@ -52,6 +59,57 @@ impl GameInfo {
     pub fn get_save_path_append(&self, next_dir: &str) -> PathBuf {
         self.path.join("save").join(next_dir)
     }
+
+    /// Looks up an instance by its id, falling back to the `aliases` declared by instances.
+    pub fn get_instance(&self, id: &str) -> Option<&Arc<InstanceInfo>> {
+        self.instances.get(id).or_else(|| {
+            self.instance_aliases
+                .get(id)
+                .and_then(|real_id| self.instances.get(real_id))
+        })
+    }
+
+    /// What keeps an instance from being played, content missing since it was loaded included.
+    pub fn get_instance_problems(&self, instance: &InstanceInfo) -> Vec<String> {
+        let mut problems = Vec::new();
+        match self.indexes.get(&instance.index) {
+            Some(index) if !index.is_file() => {
+                problems.push(format!("Index file of '{}' is missing", instance.index));
+            }
+            Some(_) => {}
+            None => problems.push(format!("Index '{}' not found", instance.index)),
+        }
+        for layer_id in &instance.layers {
+            match self.layers.get(layer_id) {
+                Some(layer) if !layer.get_path().is_dir() => {
+                    problems.push(format!("Layer folder of '{}' is missing", layer_id));
+                }
+                Some(_) => {}
+                None => problems.push(format!("Layer '{}' not found", layer_id)),
+            }
+        }
+        if self.game_def.use_mods {
+            for mod_id in &instance.mods {
+                match self.mods.get(mod_id) {
+                    Some(mod_info) if !mod_info.is_file() => {
+                        problems.push(format!("Mod file of '{}' is missing", mod_id));
+                    }
+                    Some(_) => {}
+                    None => problems.push(format!("Mod '{}' not found", mod_id)),
+                }
+            }
+        }
+        if instance.get_fs().is_none() {
+            problems.push(String::from("File system not built"));
+        }
+        problems
+    }
+
+    /// Checks the game's access control list and, for API tokens, the games the token is scoped to.
+    pub fn allows(&self, identity: Option<&Identity>) -> bool {
+        self.game_def.access.allows(identity)
+            && identity.is_none_or(|identity| identity.allows_game(&self.id))
+    }
 }
 
 pub trait FileInfo {
@@ -75,6 +133,7 @@ pub trait FileInfo {
     }
 }
 
+#[derive(Clone)]
 pub struct IndexInfo {
     pub id: String,
     pub path: PathBuf,
@@ -99,10 +158,11 @@ impl FileInfo for IndexInfo {
     }
 }
 
+#[derive(Clone)]
 pub struct LayerInfo {
     pub id: String,
     pub path: PathBuf,
-    fs: Option<LayerFS>,
+    fs: Option<Arc<LayerFS>>,
 }
 
 impl FileInfo for LayerInfo {
@@ -126,12 +186,13 @@ impl LayerInfo {
 
     pub fn get_fs(&mut self) -> Result<&LayerFS> {
         if self.fs.is_none() {
-            self.fs = Some(LayerFS::new(&self.id, &self.path)?);
+            self.fs = Some(Arc::new(LayerFS::new(&self.id, &self.path)?));
         }
-        Ok(self.fs.as_ref().unwrap())
+        Ok(self.fs.as_deref().unwrap())
     }
 }
 
+#[derive(Clone)]
 pub struct ModInfo {
     pub id: String,
     pub path: PathBuf,
@@ -156,7 +217,7 @@ impl ModInfo {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct InstanceInfo {
     pub id: String,
     pub index: String,
@@ -166,8 +227,21 @@ pub struct InstanceInfo {
     pub mods: Vec<String>,
     #[serde(default)]
     pub layers: Vec<String>,
+    /// Save directory under `save/`, defaults to the instance id.
+    /// Instances sharing a namespace share their saves.
+    #[serde(default)]
+    pub save_namespace: Option<String>,
+    /// Former ids which still resolve to this instance.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Checked in addition to the access control of the game.
+    #[serde(flatten)]
+    pub access: AccessControl,
     #[serde(skip)]
     pub fs: Option<InstanceFS>,
+    /// The file in `instance/` the instance was loaded from.
+    #[serde(skip)]
+    pub file: Option<PathBuf>,
 }
 
 impl InstanceInfo {
@@ -179,7 +253,50 @@ impl InstanceInfo {
         }
     }
 
+    pub fn get_save_namespace(&self) -> &str {
+        self.save_namespace.as_deref().unwrap_or(&self.id)
+    }
+
     pub fn get_fs(&self) -> Option<&InstanceFS> {
         self.fs.as_ref()
     }
+
+    /// The format of the file the instance was loaded from, its extension.
+    pub fn get_format(&self) -> Option<&str> {
+        self.file.as_deref()?.extension()?.to_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::foundation::api_token::{TokenPermission, TokenScope};
+
+    fn identity(name: &str, admin: bool, games: Option<&[&str]>) -> Identity {
+        Identity {
+            name: name.to_string(),
+            admin,
+            groups: Vec::new(),
+            scope: games.map(|games| TokenScope {
+                games: games.iter().map(|game| game.to_string()).collect(),
+                permissions: vec![TokenPermission::Admin],
+            }),
+        }
+    }
+
+    #[test]
+    fn game_access_checks_the_list_and_the_token_scope() {
+        let mut game = GameInfo::of("dol", PathBuf::from("dol"), GameDef::default());
+        assert!(game.allows(None));
+        assert!(game.allows(Some(&identity("bob", false, None))));
+        assert!(game.allows(Some(&identity("token:ci", true, Some(&["dol"])))));
+        assert!(!game.allows(Some(&identity("token:ci", true, Some(&["sc"])))));
+
+        game.game_def.access.allowed_users = vec![String::from("alice")];
+        assert!(!game.allows(None));
+        assert!(!game.allows(Some(&identity("bob", false, None))));
+        assert!(game.allows(Some(&identity("alice", false, None))));
+        assert!(game.allows(Some(&identity("root", true, None))));
+        assert!(!game.allows(Some(&identity("token:ci", true, Some(&["sc"])))));
+    }
 }
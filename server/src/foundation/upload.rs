@@ -0,0 +1,126 @@
+use crate::foundation::structure::{GameInfo, IndexInfo, LayerInfo, ModInfo};
+use crate::util::archive::extract_archive;
+use crate::util::file::is_valid_path_segment;
+use crate::util::token::random_token;
+use anyhow::{Result, anyhow};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+pub const INDEX_UPLOAD_MAX_SIZE: u64 = 64 * 1024 * 1024;
+pub const MOD_UPLOAD_MAX_SIZE: u64 = 256 * 1024 * 1024;
+pub const LAYER_UPLOAD_MAX_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+/// Limits what a layer archive may unpack to, against zip bombs.
+pub const LAYER_EXTRACTED_MAX_SIZE: u64 = 16 * 1024 * 1024 * 1024;
+
+/// Uploads are received and unpacked here before they are moved into place, so a half written
+/// file is never picked up by the registry.
+pub const STAGING_DIR_NAME: &str = ".staging";
+const STAGING_TOKEN_BYTES: usize = 8;
+
+/// A file or directory in the staging directory of a game, removed on drop unless it was placed.
+pub struct Staged {
+    path: PathBuf,
+}
+
+impl Staged {
+    pub fn new(game: &GameInfo) -> Result<Self> {
+        let staging_dir = game.path.join(STAGING_DIR_NAME);
+        fs::create_dir_all(&staging_dir)?;
+        Ok(Staged {
+            path: staging_dir.join(random_token(STAGING_TOKEN_BYTES)),
+        })
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Moves the staged file or directory to `target`, which must not exist yet.
+    pub fn place(self, target: &Path) -> Result<()> {
+        if target.exists() {
+            return Err(anyhow!("{:?} already exists", target));
+        }
+        fs::rename(&self.path, target)?;
+        Ok(())
+    }
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        if self.path.is_dir() {
+            let _ = fs::remove_dir_all(&self.path);
+        } else if self.path.exists() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Ids of uploaded content end up as file and directory names, hidden names are kept for the
+/// server itself.
+pub fn is_valid_content_id(id: &str) -> bool {
+    is_valid_path_segment(id) && !id.starts_with('.')
+}
+
+fn check_new_content(kind: &str, id: &str, exists: bool) -> Result<()> {
+    if !is_valid_content_id(id) {
+        return Err(anyhow!("Invalid {} id '{}'", kind, id));
+    }
+    if exists {
+        return Err(anyhow!("{} '{}' already exists", kind, id));
+    }
+    Ok(())
+}
+
+/// Moves a staged html file to `index/{id}.html` and registers it.
+pub fn add_index(game: &mut GameInfo, id: &str, staged: Staged) -> Result<()> {
+    check_new_content("index", id, game.indexes.contains_key(id))?;
+    if fs::metadata(staged.get_path())?.len() == 0 {
+        return Err(anyhow!("Index file is empty"));
+    }
+    let file_name = format!("{}.html", id);
+    staged.place(&game.get_index_path().join(&file_name))?;
+    game.indexes.insert(
+        id.to_string(),
+        IndexInfo::of(id, &file_name, &game.get_index_path()),
+    );
+    Ok(())
+}
+
+/// Moves a staged zip to `mod/{id}.zip` once it opens as a zip archive and registers it.
+pub fn add_mod(game: &mut GameInfo, id: &str, staged: Staged) -> Result<()> {
+    if !game.game_def.use_mods {
+        return Err(anyhow!("Mods are disabled for game {}", game.id));
+    }
+    check_new_content("mod", id, game.mods.contains_key(id))?;
+    ZipArchive::new(File::open(staged.get_path())?)
+        .map_err(|err| anyhow!("Mod is not a zip archive: {}", err))?;
+    let file_name = format!("{}.zip", id);
+    staged.place(&game.get_mod_path().join(&file_name))?;
+    game.mods.insert(
+        id.to_string(),
+        ModInfo::of(id, &file_name, &game.get_mod_path()),
+    );
+    Ok(())
+}
+
+/// Extracts a staged layer archive into a new staged directory, returns it with its number of
+/// files. This takes a while, so it is done before the game is locked for [`add_layer`].
+pub fn extract_layer(game: &GameInfo, archive: Staged) -> Result<(Staged, usize)> {
+    let extracted = Staged::new(game)?;
+    let files = extract_archive(
+        archive.get_path(),
+        extracted.get_path(),
+        LAYER_EXTRACTED_MAX_SIZE,
+    )?;
+    Ok((extracted, files))
+}
+
+/// Moves a directory staged by [`extract_layer`] to `layer/{id}` and registers it.
+pub fn add_layer(game: &mut GameInfo, id: &str, extracted: Staged) -> Result<()> {
+    check_new_content("layer", id, game.layers.contains_key(id))?;
+    extracted.place(&game.get_layer_path().join(id))?;
+    game.layers
+        .insert(id.to_string(), LayerInfo::of(id, &game.get_layer_path()));
+    Ok(())
+}
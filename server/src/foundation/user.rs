@@ -0,0 +1,405 @@
+use crate::foundation::api_token::{ApiToken, TokenPermission, TokenScope};
+use crate::util::backoff::Backoff;
+use crate::util::file::{WatchedFile, is_valid_path_segment};
+use crate::util::token::random_token;
+use anyhow::{Result, anyhow};
+use argon2::password_hash::SaltString;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+const USER_FILE_NAME: &str = "users.json";
+const SESSION_FILE_NAME: &str = "sessions.json";
+const SESSION_TOKEN_BYTES: usize = 32;
+/// Checked for unknown users, so they take as long to turn away as wrong passwords.
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("not a password").unwrap_or_default());
+
+/// A local account, the password is only kept as an argon2 hash.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct User {
+    pub name: String,
+    password_hash: String,
+    #[serde(default)]
+    pub admin: bool,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    pub created_at: String,
+}
+
+/// The authenticated user or API token of a request, put into the request extensions by the auth
+/// middleware.
+#[derive(Clone, Debug)]
+pub struct Identity {
+    pub name: String,
+    pub admin: bool,
+    pub groups: Vec<String>,
+    /// Set when the request was authenticated by an API token instead of a session.
+    pub scope: Option<TokenScope>,
+}
+
+impl Identity {
+    pub fn allows_game(&self, game_id: &str) -> bool {
+        self.scope
+            .as_ref()
+            .is_none_or(|scope| scope.allows_game(game_id))
+    }
+}
+
+impl From<&User> for Identity {
+    fn from(user: &User) -> Self {
+        Identity {
+            name: user.name.clone(),
+            admin: user.admin,
+            groups: user.groups.clone(),
+            scope: None,
+        }
+    }
+}
+
+impl From<&ApiToken> for Identity {
+    fn from(token: &ApiToken) -> Self {
+        let scope = token.get_scope();
+        Identity {
+            name: token.get_identity_name(),
+            admin: scope.has(TokenPermission::Admin),
+            groups: Vec::new(),
+            scope: Some(scope),
+        }
+    }
+}
+
+/// Users and groups allowed to access a game or instance, both empty means open to everyone.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+#[serde(default)]
+pub struct AccessControl {
+    pub allowed_users: Vec<String>,
+    pub allowed_groups: Vec<String>,
+}
+
+impl AccessControl {
+    pub fn is_restricted(&self) -> bool {
+        !self.allowed_users.is_empty() || !self.allowed_groups.is_empty()
+    }
+
+    /// Admins pass every list, anonymous requests only pass unrestricted ones.
+    pub fn allows(&self, identity: Option<&Identity>) -> bool {
+        if !self.is_restricted() {
+            return true;
+        }
+        identity.is_some_and(|identity| {
+            identity.admin
+                || self.allowed_users.contains(&identity.name)
+                || identity
+                    .groups
+                    .iter()
+                    .any(|group| self.allowed_groups.contains(group))
+        })
+    }
+}
+
+/// User accounts, persisted to `users.json` in the data directory.
+pub struct UserStore {
+    file: WatchedFile,
+    users: Mutex<HashMap<String, User>>,
+    login_attempts: Backoff,
+}
+
+impl UserStore {
+    pub fn load(data_path: &Path) -> Result<Self> {
+        let file = WatchedFile::new(data_path.join(USER_FILE_NAME));
+        let users = read_users(file.get_path())?;
+        info!("Loaded {} users", users.len());
+
+        Ok(UserStore {
+            file,
+            users: Mutex::new(users),
+            login_attempts: Backoff::default(),
+        })
+    }
+
+    /// Locks the users, reading them again first when a CLI command changed the file.
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, User>> {
+        let mut users = self.users.lock().unwrap();
+        if self.file.is_changed() {
+            match read_users(self.file.get_path()) {
+                Ok(reloaded) => {
+                    info!("Reloaded {} users", reloaded.len());
+                    *users = reloaded;
+                    self.file.update();
+                }
+                Err(err) => error!("Failed to reload {}: {}", USER_FILE_NAME, err),
+            }
+        }
+        users
+    }
+
+    pub fn add(&self, name: &str, password: &str, admin: bool, groups: Vec<String>) -> Result<()> {
+        if !is_valid_path_segment(name) || name.contains('@') {
+            return Err(anyhow!("Invalid user name '{}'", name));
+        }
+
+        let mut users = self.lock();
+        if users.contains_key(name) {
+            return Err(anyhow!("User '{}' already exists", name));
+        }
+        users.insert(
+            name.to_string(),
+            User {
+                name: name.to_string(),
+                password_hash: hash_password(password)?,
+                admin,
+                groups,
+                created_at: Local::now().to_rfc3339(),
+            },
+        );
+        self.persist(&users)
+    }
+
+    pub fn set_password(&self, name: &str, password: &str) -> Result<()> {
+        let mut users = self.lock();
+        let user = users
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("User '{}' does not exist", name))?;
+        user.password_hash = hash_password(password)?;
+        self.persist(&users)
+    }
+
+    pub fn set_groups(&self, name: &str, groups: Vec<String>) -> Result<()> {
+        let mut users = self.lock();
+        let user = users
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("User '{}' does not exist", name))?;
+        user.groups = groups;
+        self.persist(&users)
+    }
+
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let mut users = self.lock();
+        if users.remove(name).is_none() {
+            return Err(anyhow!("User '{}' does not exist", name));
+        }
+        self.persist(&users)
+    }
+
+    pub fn get(&self, name: &str) -> Option<User> {
+        self.lock().get(name).cloned()
+    }
+
+    pub fn all(&self) -> Vec<User> {
+        let mut users: Vec<User> = self.lock().values().cloned().collect();
+        users.sort_by(|a, b| a.name.cmp(&b.name));
+        users
+    }
+
+    /// Returns the user when `password` matches, unknown users and wrong passwords are not told apart.
+    /// Repeated failed logins for a name from `client` have to wait longer and longer. Checking a
+    /// password is slow, call this from a blocking thread.
+    pub fn verify(&self, name: &str, password: &str, client: Option<&str>) -> Result<User> {
+        if let Err(wait) = self.login_attempts.try_attempt(name, client) {
+            return Err(anyhow!(
+                "Too many failed logins, try again in {} seconds",
+                wait.as_secs() + 1
+            ));
+        }
+        let user = self.get(name);
+        let password_hash = user
+            .as_ref()
+            .map_or(DUMMY_PASSWORD_HASH.as_str(), |user| &user.password_hash);
+        let matches = verify_password(password_hash, password);
+        let user = user
+            .filter(|_| matches)
+            .ok_or_else(|| anyhow!("Invalid user name or password"))?;
+        self.login_attempts.reset(name, client);
+        Ok(user)
+    }
+
+    fn persist(&self, users: &HashMap<String, User>) -> Result<()> {
+        self.file
+            .write(&serde_json::to_string_pretty(users)?)
+            .inspect_err(|err| {
+                error!("Failed to write {}: {}", USER_FILE_NAME, err);
+            })?;
+        Ok(())
+    }
+}
+
+fn read_users(path: &Path) -> Result<HashMap<String, User>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+pub(crate) fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| anyhow!("Failed to hash password: {}", err))
+}
+
+pub(crate) fn verify_password(password_hash: &str, password: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .inspect_err(|err| error!("Invalid password hash: {}", err))
+        .is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+    pub user: String,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+impl Session {
+    pub fn is_expired(&self) -> bool {
+        DateTime::parse_from_rfc3339(&self.expires_at)
+            .map_or(true, |expires_at| expires_at < Local::now())
+    }
+}
+
+/// Login sessions keyed by the token in the session cookie, persisted to `sessions.json` so
+/// restarting the server does not log everyone out.
+pub struct SessionStore {
+    file: WatchedFile,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl SessionStore {
+    pub fn load(data_path: &Path) -> Result<Self> {
+        let file = WatchedFile::new(data_path.join(SESSION_FILE_NAME));
+        let mut sessions = read_sessions(file.get_path())?;
+        sessions.retain(|_, session| !session.is_expired());
+
+        Ok(SessionStore {
+            file,
+            sessions: Mutex::new(sessions),
+        })
+    }
+
+    /// Locks the sessions, reading them again first when a CLI command changed the file.
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Session>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if self.file.is_changed() {
+            match read_sessions(self.file.get_path()) {
+                Ok(reloaded) => {
+                    info!("Reloaded {} sessions", reloaded.len());
+                    *sessions = reloaded;
+                    self.file.update();
+                }
+                Err(err) => error!("Failed to reload {}: {}", SESSION_FILE_NAME, err),
+            }
+        }
+        sessions
+    }
+
+    pub fn create(&self, user: &str, ttl: Duration) -> Result<String> {
+        let token = random_token(SESSION_TOKEN_BYTES);
+        let now = Local::now();
+
+        let mut sessions = self.lock();
+        sessions.retain(|_, session| !session.is_expired());
+        sessions.insert(
+            token.clone(),
+            Session {
+                user: user.to_string(),
+                created_at: now.to_rfc3339(),
+                expires_at: (now + ttl).to_rfc3339(),
+            },
+        );
+        self.persist(&sessions)?;
+
+        Ok(token)
+    }
+
+    pub fn get(&self, token: &str) -> Option<Session> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(token)
+            .filter(|session| !session.is_expired())
+            .cloned()
+    }
+
+    /// Logs a user out everywhere, after a password change or when the user is removed.
+    pub fn remove_user(&self, user: &str) -> Result<usize> {
+        let mut sessions = self.lock();
+        let before = sessions.len();
+        sessions.retain(|_, session| session.user != user);
+        let removed = before - sessions.len();
+        if removed > 0 {
+            self.persist(&sessions)?;
+        }
+        Ok(removed)
+    }
+
+    pub fn remove(&self, token: &str) -> Result<()> {
+        let mut sessions = self.lock();
+        if sessions.remove(token).is_some() {
+            self.persist(&sessions)?;
+        }
+        Ok(())
+    }
+
+    fn persist(&self, sessions: &HashMap<String, Session>) -> Result<()> {
+        self.file
+            .write(&serde_json::to_string_pretty(sessions)?)
+            .inspect_err(|err| {
+                error!("Failed to write {}: {}", SESSION_FILE_NAME, err);
+            })?;
+        Ok(())
+    }
+}
+
+fn read_sessions(path: &Path) -> Result<HashMap<String, Session>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(name: &str, admin: bool, groups: &[&str]) -> Identity {
+        Identity {
+            name: name.to_string(),
+            admin,
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            scope: None,
+        }
+    }
+
+    #[test]
+    fn unrestricted_access_allows_everyone() {
+        let access = AccessControl::default();
+        assert!(access.allows(None));
+        assert!(access.allows(Some(&identity("bob", false, &[]))));
+    }
+
+    #[test]
+    fn restricted_access_checks_users_groups_and_admins() {
+        let access = AccessControl {
+            allowed_users: vec![String::from("alice")],
+            allowed_groups: vec![String::from("testers")],
+        };
+        assert!(!access.allows(None));
+        assert!(access.allows(Some(&identity("alice", false, &[]))));
+        assert!(access.allows(Some(&identity("carol", false, &["family", "testers"]))));
+        assert!(access.allows(Some(&identity("root", true, &[]))));
+        assert!(!access.allows(Some(&identity("bob", false, &["family"]))));
+    }
+}
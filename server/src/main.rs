@@ -1,35 +1,83 @@
+mod cli;
 mod constants;
 mod foundation;
 mod router;
 mod util;
 
+use crate::cli::{Cli, run_command};
+use crate::foundation::api_token::ApiTokenStore;
+use crate::foundation::audit::AuditLog;
 use crate::foundation::config::{CONFIG, init_config};
+use crate::foundation::event::SaveEventHub;
+use crate::foundation::household::HouseholdStore;
+use crate::foundation::inbox::spawn_inbox_watcher;
 use crate::foundation::registry::init_registry;
-use crate::router::get_router;
+use crate::foundation::share::ShareStore;
+use crate::foundation::user::{SessionStore, UserStore};
+use crate::router::{authenticate, check_hosts, get_router, rewrite_host_request};
 use crate::util::AppState;
 use anyhow::Result;
-use axum::Router;
+use axum::extract::Request;
+use axum::middleware;
+use axum::{Router, ServiceExt};
+use clap::Parser;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::net::TcpListener;
+use tower::Layer;
+use tower::util::MapRequestLayer;
 use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
 
     init_config()?;
+    let config = CONFIG.get().expect("Config not initialized.");
+
+    if let Some(command) = cli.command {
+        return run_command(config, command);
+    }
+
     let registry = init_registry()?;
+    let shares = ShareStore::load(&config.get_data_path())?;
+    let users = UserStore::load(&config.get_data_path())?;
+    let sessions = SessionStore::load(&config.get_data_path())?;
+    let tokens = ApiTokenStore::load(&config.get_data_path())?;
+    let households = HouseholdStore::load(&config.get_data_path())?;
+    let audit = AuditLog::open(&config.get_data_path())?;
+
+    check_hosts(&config.hosts, &registry);
 
-    let port = CONFIG.get().expect("Config not initialized.").port;
+    let port = config.port;
     let addr = format!("0.0.0.0:{port}");
 
+    let state = Arc::new(AppState {
+        registry,
+        shares,
+        events: SaveEventHub::new(),
+        users,
+        sessions,
+        tokens,
+        households,
+        audit,
+    });
+    spawn_inbox_watcher(state.clone());
     let app = Router::new()
-        .merge(get_router())
-        .with_state(Arc::new(AppState { registry }));
+        .merge(get_router(state.clone()))
+        .layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .with_state(state);
     let listener = TcpListener::bind(&addr).await?;
     info!("listening on {addr}");
 
-    axum::serve(listener, app).await?;
+    // Hosts are resolved before routing, so the rewrite wraps the whole router instead of being a route layer.
+    let app = MapRequestLayer::new(rewrite_host_request).layer(app);
+    axum::serve(
+        listener,
+        ServiceExt::<Request>::into_make_service_with_connect_info::<SocketAddr>(app),
+    )
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,226 @@
+use crate::foundation::api_token::{ApiToken, TokenPermission};
+use crate::foundation::audit::{Actor, AuditAction, AuditEntry, AuditQuery, AuditTarget};
+use crate::foundation::registry::Registry;
+use crate::foundation::structure::GameInfo;
+use crate::foundation::user::Identity;
+use crate::util::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get};
+use axum::{Extension, Json, Router, middleware};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use utoipa::{OpenApi, ToSchema};
+
+/// Admin endpoints, every route requires an admin user or an admin token.
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/tokens",
+            get(handle_token_list).post(handle_token_create),
+        )
+        .route("/api/tokens/{token_id}", delete(handle_token_revoke))
+        .route("/api/audit", get(handle_audit_query))
+        .route_layer(middleware::from_fn(super::auth::require_admin))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    handle_token_list,
+    handle_token_create,
+    handle_token_revoke,
+    handle_audit_query
+))]
+pub(super) struct AdminApi;
+
+#[utoipa::path(
+    get,
+    path = "/api/tokens",
+    tag = "admin",
+    responses((status = 200, description = "Every API token, without secrets", body = Vec<ApiToken>))
+)]
+async fn handle_token_list(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.tokens.all())
+}
+
+#[derive(Deserialize, ToSchema)]
+struct TokenCreateRequest {
+    name: String,
+    /// Games the token is scoped to, every game when empty.
+    #[serde(default)]
+    games: Vec<String>,
+    permissions: Vec<TokenPermission>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct TokenCreateResponse {
+    #[serde(flatten)]
+    token: ApiToken,
+    /// Only returned here, the server keeps nothing but its hash.
+    secret: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/tokens",
+    tag = "admin",
+    request_body = TokenCreateRequest,
+    responses(
+        (status = 201, description = "The token and its secret", body = TokenCreateResponse),
+        (status = 400, description = "Unknown game or invalid token"),
+        (status = 403, description = "The token scope exceeds the caller's own")
+    )
+)]
+async fn handle_token_create(
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    Json(request): Json<TokenCreateRequest>,
+) -> impl IntoResponse {
+    if let Some(game_id) = request
+        .games
+        .iter()
+        .find(|game_id| state.registry.get(game_id).is_none())
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("no game found with id {}", game_id),
+        )
+            .into_response();
+    }
+    // A scoped admin token must not hand out tokens for games outside its own scope.
+    let within_scope = match identity
+        .scope
+        .as_ref()
+        .filter(|scope| !scope.games.is_empty())
+    {
+        Some(scope) => {
+            !request.games.is_empty()
+                && request
+                    .games
+                    .iter()
+                    .all(|game_id| scope.allows_game(game_id))
+        }
+        None => true,
+    };
+    if !within_scope {
+        return (StatusCode::FORBIDDEN, "Token scope exceeds your own").into_response();
+    }
+
+    match state
+        .tokens
+        .create(&request.name, request.games, request.permissions)
+    {
+        Ok((token, secret)) => {
+            info!(
+                "API token {} ({}) created by {}",
+                token.id, token.name, identity.name
+            );
+            state.audit.record(
+                &actor,
+                AuditAction::TokenCreate,
+                AuditTarget {
+                    target: Some(&token.id),
+                    ..Default::default()
+                },
+            );
+            (
+                StatusCode::CREATED,
+                Json(TokenCreateResponse { token, secret }),
+            )
+                .into_response()
+        }
+        Err(err) => (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/tokens/{token_id}",
+    tag = "admin",
+    params(("token_id", description = "Token id")),
+    responses(
+        (status = 204, description = "The token was revoked"),
+        (status = 404, description = "Unknown token")
+    )
+)]
+async fn handle_token_revoke(
+    Path(token_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+) -> impl IntoResponse {
+    match state.tokens.revoke(&token_id) {
+        Ok(token) => {
+            info!(
+                "API token {} ({}) revoked by {}",
+                token.id, token.name, identity.name
+            );
+            state.audit.record(
+                &actor,
+                AuditAction::TokenRevoke,
+                AuditTarget {
+                    target: Some(&token.id),
+                    ..Default::default()
+                },
+            );
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(err) => {
+            warn!("Failed to revoke API token {}: {}", token_id, err);
+            (StatusCode::NOT_FOUND, err.to_string()).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/audit",
+    tag = "admin",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Matching entries, newest first", body = Vec<AuditEntry>),
+        (status = 400, description = "Invalid query")
+    )
+)]
+async fn handle_audit_query(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AuditQuery>,
+) -> impl IntoResponse {
+    // The whole log is read, keep it off the async workers.
+    match tokio::task::spawn_blocking(move || state.audit.query(&query)).await {
+        Ok(Ok(entries)) => Json(entries).into_response(),
+        Ok(Err(err)) => (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid audit query: {}", err),
+        )
+            .into_response(),
+        Err(err) => {
+            error!("Failed to query the audit log: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
+
+/// Looks up a game the caller may manage, scoped admin tokens only manage their games.
+pub(super) fn get_managed_game(
+    state: &AppState,
+    identity: &Identity,
+    game_id: &str,
+) -> Result<Arc<GameInfo>, (StatusCode, String)> {
+    let game = state.registry.get(game_id).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("no game found with id {}", game_id),
+        )
+    })?;
+    if !identity.allows_game(game_id) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            String::from("Token not scoped to this game"),
+        ));
+    }
+    Ok(game)
+}
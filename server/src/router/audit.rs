@@ -0,0 +1,33 @@
+use crate::foundation::audit::Actor;
+use crate::foundation::household::HouseholdProfile;
+use crate::foundation::user::Identity;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::Extensions;
+use axum::http::request::Parts;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+/// The actor of a request, a logged in user or token wins over a household profile.
+pub fn get_actor(extensions: &Extensions) -> Actor {
+    let name = extensions
+        .get::<Identity>()
+        .map(|identity| identity.name.clone())
+        .or_else(|| {
+            extensions
+                .get::<HouseholdProfile>()
+                .map(|profile| format!("household:{}", profile.name))
+        });
+    let ip = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+    Actor { name, ip }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Actor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(get_actor(&parts.extensions))
+    }
+}
@@ -0,0 +1,344 @@
+use super::audit::get_actor;
+use super::household::HOUSEHOLD_COOKIE;
+use crate::foundation::audit::{Actor, AuditAction, AuditTarget};
+use crate::foundation::config::CONFIG;
+use crate::foundation::registry::Registry;
+use crate::foundation::user::Identity;
+use crate::util::AppState;
+use askama::Template;
+use axum::extract::{OriginalUri, Query, RawPathParams, Request, State};
+use axum::http::header::{ACCEPT, AUTHORIZATION, COOKIE, SET_COOKIE};
+use axum::http::{HeaderMap, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::{Form, Router};
+use chrono::Duration;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+pub(super) const SESSION_COOKIE: &str = "session";
+
+/// Paths reachable without a session even when `require_login` is set.
+const PUBLIC_PATH_PREFIXES: [&str; 4] = ["/login", "/logout", "/favicon.ico", "/share/"];
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/login", get(handle_login_page).post(handle_login))
+        .route("/logout", post(handle_logout))
+}
+
+/// Resolves the bearer token or session cookie into an [`Identity`] request extension, along with
+/// the picked household profile, and turns away anonymous requests when the server requires a login.
+pub async fn authenticate(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Some(secret) = get_bearer_token(request.headers()) {
+        let mut actor = get_actor(request.extensions());
+        let path = request.uri().path().to_string();
+        let Some(token) = state.tokens.authenticate(secret) else {
+            warn!("Rejected invalid API token for {}", path);
+            state.audit.record(
+                &actor,
+                AuditAction::TokenRejected,
+                AuditTarget {
+                    target: Some(&path),
+                    ..Default::default()
+                },
+            );
+            return (StatusCode::UNAUTHORIZED, "Invalid API token").into_response();
+        };
+        let identity = Identity::from(&token);
+        info!(
+            "API token {} ({}) used: {} {}",
+            token.id,
+            token.name,
+            request.method(),
+            path
+        );
+        actor.name = Some(identity.name.clone());
+        let target = format!("{} {}", request.method(), path);
+        state.audit.record(
+            &actor,
+            AuditAction::TokenUse,
+            AuditTarget {
+                target: Some(&target),
+                ..Default::default()
+            },
+        );
+        if identity
+            .scope
+            .as_ref()
+            .is_some_and(|scope| !scope.allows_method(request.method()))
+        {
+            state.audit.record(
+                &actor,
+                AuditAction::AccessDenied,
+                AuditTarget {
+                    target: Some(&target),
+                    ..Default::default()
+                },
+            );
+            return (StatusCode::FORBIDDEN, "API token lacks permission").into_response();
+        }
+        request.extensions_mut().insert(identity);
+        return next.run(request).await;
+    }
+
+    if let Some(profile) = get_cookie(request.headers(), HOUSEHOLD_COOKIE)
+        .and_then(|token| state.households.get_selected(token))
+    {
+        request.extensions_mut().insert(profile);
+    }
+
+    let identity = get_cookie(request.headers(), SESSION_COOKIE)
+        .and_then(|token| state.sessions.get(token))
+        .and_then(|session| state.users.get(&session.user))
+        .map(|user| Identity::from(&user));
+
+    match identity {
+        Some(identity) => {
+            request.extensions_mut().insert(identity);
+        }
+        None => {
+            let require_login = CONFIG.get().is_some_and(|config| config.require_login);
+            let path = request.uri().path();
+            if require_login
+                && !PUBLIC_PATH_PREFIXES
+                    .iter()
+                    .any(|prefix| path.starts_with(prefix))
+            {
+                return reject_anonymous(&request);
+            }
+        }
+    }
+
+    next.run(request).await
+}
+
+/// Enforces the access control of the game and instance named by the `game_id` and `instance_id`
+/// path parameters, unknown games and instances are left to the handlers.
+pub async fn authorize(
+    State(state): State<Arc<AppState>>,
+    params: RawPathParams,
+    request: Request,
+    next: Next,
+) -> Response {
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    };
+    let identity = request.extensions().get::<Identity>();
+
+    if let Some(game) = param("game_id").and_then(|game_id| state.registry.get(game_id)) {
+        let instance = param("instance_id").and_then(|instance_id| game.get_instance(instance_id));
+        if !game.allows(identity)
+            || instance.is_some_and(|instance| !instance.access.allows(identity))
+        {
+            let path = request
+                .extensions()
+                .get::<OriginalUri>()
+                .map_or(request.uri().path(), |uri| uri.path());
+            warn!(
+                "Access denied to {} for {}",
+                path,
+                identity.map_or("anonymous", |identity| identity.name.as_str())
+            );
+            state.audit.record(
+                &get_actor(request.extensions()),
+                AuditAction::AccessDenied,
+                AuditTarget {
+                    game: Some(&game.id),
+                    instance: instance.map(|instance| instance.id.as_str()),
+                    target: Some(path),
+                },
+            );
+            return (StatusCode::FORBIDDEN, "Access denied").into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+/// Browsers navigating to a page are sent to the login page, everything else gets a plain 401.
+fn reject_anonymous(request: &Request) -> Response {
+    let wants_html = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+
+    if request.method() == Method::GET && wants_html {
+        let next = request
+            .uri()
+            .path_and_query()
+            .map_or("/", |path| path.as_str());
+        let query = serde_urlencoded::to_string([("next", next)]).unwrap_or_default();
+        Redirect::to(&format!("/login?{}", query)).into_response()
+    } else {
+        (StatusCode::UNAUTHORIZED, "Login required").into_response()
+    }
+}
+
+/// Reads `Authorization: Bearer <secret>`, other schemes are ignored.
+fn get_bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Lets through admins, whether logged in users or tokens with the `admin` permission.
+pub async fn require_admin(request: Request, next: Next) -> Response {
+    match request.extensions().get::<Identity>() {
+        Some(identity) if identity.admin => next.run(request).await,
+        Some(_) => (StatusCode::FORBIDDEN, "Admin access required").into_response(),
+        None => (StatusCode::UNAUTHORIZED, "Login required").into_response(),
+    }
+}
+
+pub(super) fn get_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Only local paths are followed after login, so the login page cannot redirect off-site.
+pub(super) fn safe_next(next: Option<&str>) -> &str {
+    next.filter(|next| next.starts_with('/') && !next.starts_with("//") && !next.contains('\\'))
+        .unwrap_or("/")
+}
+
+#[derive(Template)]
+#[template(path = "login.html")]
+struct LoginTemplate {
+    next: String,
+    error: Option<String>,
+}
+
+fn render_login(next: &str, error: Option<&str>, status: StatusCode) -> Response {
+    let template = LoginTemplate {
+        next: next.to_string(),
+        error: error.map(str::to_string),
+    };
+    match template.render() {
+        Ok(html) => (status, Html(html)).into_response(),
+        Err(err) => {
+            error!("Failed to render login page: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct LoginQuery {
+    next: Option<String>,
+}
+
+async fn handle_login_page(Query(query): Query<LoginQuery>) -> impl IntoResponse {
+    render_login(safe_next(query.next.as_deref()), None, StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    username: String,
+    password: String,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+async fn handle_login(
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    Form(form): Form<LoginForm>,
+) -> impl IntoResponse {
+    let next = safe_next(form.next.as_deref());
+    // The password hash is checked on a blocking thread, it takes a while on purpose.
+    let verified = {
+        let state = state.clone();
+        let (name, password, client) = (
+            form.username.clone(),
+            form.password.clone(),
+            actor.ip.clone(),
+        );
+        tokio::task::spawn_blocking(move || state.users.verify(&name, &password, client.as_deref()))
+            .await
+            .unwrap_or_else(|err| Err(err.into()))
+    };
+    let user = match verified {
+        Ok(user) => user,
+        Err(err) => {
+            warn!("Failed login attempt for {}: {}", form.username, err);
+            state.audit.record(
+                &actor,
+                AuditAction::LoginFailed,
+                AuditTarget {
+                    target: Some(&form.username),
+                    ..Default::default()
+                },
+            );
+            return render_login(next, Some(&err.to_string()), StatusCode::UNAUTHORIZED);
+        }
+    };
+
+    let ttl_hours = CONFIG.get().map_or(24, |config| config.session_ttl_hours);
+    match state
+        .sessions
+        .create(&user.name, Duration::hours(ttl_hours.into()))
+    {
+        Ok(token) => {
+            info!("User logged in: {}", user.name);
+            let actor = Actor {
+                name: Some(user.name.clone()),
+                ..actor
+            };
+            state
+                .audit
+                .record(&actor, AuditAction::Login, AuditTarget::default());
+            let cookie = format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+                SESSION_COOKIE,
+                token,
+                u64::from(ttl_hours) * 3600
+            );
+            ([(SET_COOKIE, cookie)], Redirect::to(next)).into_response()
+        }
+        Err(err) => {
+            error!("Failed to create session for {}: {}", user.name, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn handle_logout(
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Some(token) = get_cookie(&headers, SESSION_COOKIE) {
+        if let Err(err) = state.sessions.remove(token) {
+            error!("Failed to remove session: {}", err);
+        }
+        state
+            .audit
+            .record(&actor, AuditAction::Logout, AuditTarget::default());
+    }
+
+    let cookie = format!(
+        "{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0",
+        SESSION_COOKIE
+    );
+    ([(SET_COOKIE, cookie)], Redirect::to("/login")).into_response()
+}
@@ -0,0 +1,292 @@
+use super::instance::InstanceView;
+use crate::foundation::registry::Registry;
+use crate::foundation::structure::{GameInfo, InstanceInfo};
+use crate::foundation::user::Identity;
+use crate::util::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Json, Router, middleware};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use utoipa::{OpenApi, ToSchema};
+
+/// Read-only description of the games and instances the caller may access, the same ones the index
+/// page lists.
+pub fn routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/games", get(handle_game_list))
+        .route("/api/games/{game_id}", get(handle_game_get))
+        .route("/api/games/{game_id}/instances", get(handle_instance_list))
+        .route(
+            "/api/games/{game_id}/instances/{instance_id}",
+            get(handle_instance_get),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state,
+            super::auth::authorize,
+        ))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    handle_game_list,
+    handle_game_get,
+    handle_instance_list,
+    handle_instance_get
+))]
+pub(super) struct GameApi;
+
+/// Where an instance is played and whether it can be.
+#[derive(Serialize, ToSchema)]
+pub(super) struct InstanceStatus {
+    url: String,
+    available: bool,
+    /// Why the instance is not available.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    problems: Vec<String>,
+}
+
+impl InstanceStatus {
+    pub(super) fn of(game: &GameInfo, instance: &InstanceInfo) -> Self {
+        let problems = game.get_instance_problems(instance);
+        InstanceStatus {
+            url: format!("/play/{}/{}/index", game.id, instance.id),
+            available: problems.is_empty(),
+            problems,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct InstanceSummary<'a> {
+    id: &'a str,
+    name: String,
+    index: &'a str,
+    layers: &'a [String],
+    mods: &'a [String],
+    aliases: &'a [String],
+    save_namespace: &'a str,
+    #[serde(flatten)]
+    status: InstanceStatus,
+}
+
+impl<'a> InstanceSummary<'a> {
+    fn of(game: &GameInfo, instance: &'a InstanceInfo) -> Self {
+        InstanceSummary {
+            id: &instance.id,
+            name: instance.get_name(),
+            index: &instance.index,
+            layers: &instance.layers,
+            mods: &instance.mods,
+            aliases: &instance.aliases,
+            save_namespace: instance.get_save_namespace(),
+            status: InstanceStatus::of(game, instance),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GameSummary<'a> {
+    id: &'a str,
+    name: &'a str,
+    /// Instances the caller may access.
+    instances: usize,
+    /// Of those, the ones which can be played.
+    available_instances: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GameView<'a> {
+    id: &'a str,
+    name: &'a str,
+    use_mods: bool,
+    indexes: Vec<&'a str>,
+    layers: Vec<&'a str>,
+    mods: Vec<&'a str>,
+    instances: Vec<InstanceSummary<'a>>,
+}
+
+fn get_game_name(game: &GameInfo) -> &str {
+    game.game_def.name.as_deref().unwrap_or(&game.id)
+}
+
+/// The instances of a game the caller may access, sorted by id.
+fn get_visible_instances<'a>(
+    game: &'a GameInfo,
+    identity: Option<&Identity>,
+) -> Vec<&'a InstanceInfo> {
+    let mut instances: Vec<&InstanceInfo> = game
+        .instances
+        .values()
+        .map(Arc::as_ref)
+        .filter(|instance| instance.access.allows(identity))
+        .collect();
+    instances.sort_by(|a, b| a.id.cmp(&b.id));
+    instances
+}
+
+fn sorted_ids<T>(map: &HashMap<String, T>) -> Vec<&str> {
+    let mut ids: Vec<&str> = map.keys().map(String::as_str).collect();
+    ids.sort();
+    ids
+}
+
+fn game_not_found(game_id: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        format!("no game found with id {}", game_id),
+    )
+        .into_response()
+}
+
+/// Admins managing a game get instances as stored in their files, to edit and put them back.
+fn manages_game(identity: Option<&Identity>, game_id: &str) -> bool {
+    identity.is_some_and(|identity| identity.admin && identity.allows_game(game_id))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/games",
+    tag = "games",
+    responses((status = 200, description = "Games the caller may access", body = Vec<GameSummary>))
+)]
+async fn handle_game_list(
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+) -> impl IntoResponse {
+    let identity = identity.map(|Extension(identity)| identity);
+    let games = state.registry.all();
+    let mut summaries: Vec<GameSummary> = games
+        .iter()
+        .map(|(_, game)| game.as_ref())
+        .filter(|game| game.allows(identity.as_ref()))
+        .map(|game| {
+            let instances = get_visible_instances(game, identity.as_ref());
+            GameSummary {
+                id: &game.id,
+                name: get_game_name(game),
+                instances: instances.len(),
+                available_instances: instances
+                    .iter()
+                    .filter(|instance| game.get_instance_problems(instance).is_empty())
+                    .count(),
+            }
+        })
+        .collect();
+    summaries.sort_by(|a, b| a.id.cmp(b.id));
+    Json(summaries).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/games/{game_id}",
+    tag = "games",
+    params(("game_id", description = "Game id")),
+    responses(
+        (status = 200, description = "The game and the instances the caller may access", body = GameView),
+        (status = 403, description = "The caller may not access the game"),
+        (status = 404, description = "Unknown game")
+    )
+)]
+async fn handle_game_get(
+    Path(game_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+) -> impl IntoResponse {
+    let identity = identity.map(|Extension(identity)| identity);
+    let Some(game) = state.registry.get(&game_id) else {
+        return game_not_found(&game_id);
+    };
+
+    Json(GameView {
+        id: &game.id,
+        name: get_game_name(&game),
+        use_mods: game.game_def.use_mods,
+        indexes: sorted_ids(&game.indexes),
+        layers: sorted_ids(&game.layers),
+        mods: sorted_ids(&game.mods),
+        instances: get_visible_instances(&game, identity.as_ref())
+            .into_iter()
+            .map(|instance| InstanceSummary::of(&game, instance))
+            .collect(),
+    })
+    .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/games/{game_id}/instances",
+    tag = "games",
+    params(("game_id", description = "Game id")),
+    responses(
+        (status = 200, description = "Instances the caller may access, admins managing the game get `InstanceView`s", body = Vec<InstanceSummary>),
+        (status = 403, description = "The caller may not access the game"),
+        (status = 404, description = "Unknown game")
+    )
+)]
+async fn handle_instance_list(
+    Path(game_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+) -> impl IntoResponse {
+    let identity = identity.map(|Extension(identity)| identity);
+    let Some(game) = state.registry.get(&game_id) else {
+        return game_not_found(&game_id);
+    };
+
+    let instances = get_visible_instances(&game, identity.as_ref());
+    if manages_game(identity.as_ref(), &game_id) {
+        let views: Vec<InstanceView> = instances
+            .into_iter()
+            .map(|instance| InstanceView::of(&game, instance))
+            .collect();
+        Json(views).into_response()
+    } else {
+        let summaries: Vec<InstanceSummary> = instances
+            .into_iter()
+            .map(|instance| InstanceSummary::of(&game, instance))
+            .collect();
+        Json(summaries).into_response()
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/games/{game_id}/instances/{instance_id}",
+    tag = "games",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    responses(
+        (status = 200, description = "The instance, admins managing the game get an `InstanceView`", body = InstanceSummary),
+        (status = 403, description = "The caller may not access the game or instance"),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
+async fn handle_instance_get(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+) -> impl IntoResponse {
+    let identity = identity.map(|Extension(identity)| identity);
+    let Some(game) = state.registry.get(&game_id) else {
+        return game_not_found(&game_id);
+    };
+    let Some(instance) = game.get_instance(&instance_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("no instance found with id {}", instance_id),
+        )
+            .into_response();
+    };
+
+    if manages_game(identity.as_ref(), &game_id) {
+        Json(InstanceView::of(&game, instance)).into_response()
+    } else {
+        Json(InstanceSummary::of(&game, instance)).into_response()
+    }
+}
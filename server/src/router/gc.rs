@@ -0,0 +1,159 @@
+use super::admin::get_managed_game;
+use crate::foundation::audit::{Actor, AuditAction, AuditTarget};
+use crate::foundation::gc::{
+    AssetRef, GcMode, GcReport, collect_unused_assets, find_unused_assets, select_unused_assets,
+};
+use crate::foundation::user::Identity;
+use crate::util::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Extension, Json, Router, middleware};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use utoipa::{OpenApi, ToSchema};
+
+/// Admin endpoints reporting and collecting the unused content of a game.
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/games/{game_id}/gc",
+            get(handle_gc_report).post(handle_gc_collect),
+        )
+        .route_layer(middleware::from_fn(super::auth::require_admin))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(handle_gc_report, handle_gc_collect))]
+pub(super) struct GcApi;
+
+#[utoipa::path(
+    get,
+    path = "/api/games/{game_id}/gc",
+    tag = "content",
+    params(("game_id", description = "Game id")),
+    responses(
+        (status = 200, description = "Indexes, layers and mods no instance uses", body = GcReport),
+        (status = 404, description = "Unknown game")
+    )
+)]
+async fn handle_gc_report(
+    Path(game_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+) -> impl IntoResponse {
+    let game = match get_managed_game(&state, &identity, &game_id) {
+        Ok(game) => game,
+        Err(err) => return err.into_response(),
+    };
+
+    // Layers can hold many files, keep walking them off the async workers.
+    match tokio::task::spawn_blocking(move || find_unused_assets(&game)).await {
+        Ok(Ok(report)) => Json(report).into_response(),
+        Ok(Err(err)) => {
+            error!("Failed to find unused assets of {}: {}", game_id, err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct GcRequest {
+    mode: GcMode,
+    /// The assets of a report the caller agreed to collect.
+    assets: Vec<AssetRef>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_id}/gc",
+    tag = "content",
+    params(("game_id", description = "Game id")),
+    request_body = GcRequest,
+    responses(
+        (status = 200, description = "The collected assets", body = GcReport),
+        (status = 400, description = "No assets confirmed"),
+        (status = 404, description = "Unknown game"),
+        (status = 409, description = "An asset is no longer unused")
+    )
+)]
+async fn handle_gc_collect(
+    Path(game_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    Json(request): Json<GcRequest>,
+) -> impl IntoResponse {
+    let game = match get_managed_game(&state, &identity, &game_id) {
+        Ok(game) => game,
+        Err(err) => return err.into_response(),
+    };
+    if request.assets.is_empty() {
+        return (StatusCode::BAD_REQUEST, "No assets confirmed").into_response();
+    }
+
+    let collected = {
+        let state = state.clone();
+        let game_id = game_id.clone();
+        tokio::task::spawn_blocking(move || {
+            // The assets are measured on a snapshot, the locked game only checks them again.
+            let assets = match find_unused_assets(&game)
+                .and_then(|report| select_unused_assets(report, &request.assets))
+            {
+                Ok(assets) => assets,
+                Err(err) => return Some(Err(err)),
+            };
+            let collected = state.registry.update(&game_id, |game| {
+                collect_unused_assets(game, assets, request.mode)
+            });
+            // Removing deleted assets waits until the game is unlocked again.
+            collected.map(|collected| collected.map(|(report, _trash)| report))
+        })
+        .await
+    };
+    let report = match collected {
+        Ok(Some(Ok(report))) => report,
+        Ok(Some(Err(err))) => {
+            warn!("Rejected collecting assets of {}: {}", game_id, err);
+            return (StatusCode::CONFLICT, err.to_string()).into_response();
+        }
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("no game found with id {}", game_id),
+            )
+                .into_response();
+        }
+        Err(err) => {
+            error!("Failed to collect assets of {}: {}", game_id, err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+        }
+    };
+
+    let action = match request.mode {
+        GcMode::Archive => AuditAction::GcArchive,
+        GcMode::Delete => AuditAction::GcDelete,
+    };
+    for unused in &report.assets {
+        state.audit.record(
+            &actor,
+            action,
+            AuditTarget {
+                game: Some(&game_id),
+                target: Some(&unused.asset.to_string()),
+                ..Default::default()
+            },
+        );
+    }
+    info!(
+        "Collected {} assets ({} bytes) of {} by {}",
+        report.assets.len(),
+        report.total.bytes,
+        game_id,
+        identity.name
+    );
+    Json(report).into_response()
+}
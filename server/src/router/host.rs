@@ -0,0 +1,96 @@
+use crate::foundation::config::{CONFIG, HostDef};
+use crate::foundation::registry::{GameRegistry, Registry};
+use axum::extract::Request;
+use axum::http::Uri;
+use axum::http::header::HOST;
+use std::collections::HashMap;
+use tracing::{debug, error, warn};
+
+/// Paths served as usual on every host together with everything below them, links into them stay
+/// absolute in pages and mod lists, and logging in works the same on every host.
+const SHARED_PATHS: [&str; 8] = [
+    "/play",
+    "/repo",
+    "/share",
+    "/api",
+    "/login",
+    "/logout",
+    "/profiles",
+    "/favicon.ico",
+];
+
+/// Maps requests to a virtual host onto the `/play` routes of its game or instance, so
+/// `beta.dol.example/index` is served like `/play/dol/beta/index`.
+pub fn rewrite_host_request(mut request: Request) -> Request {
+    let Some(host_def) = get_request_host(&request).and_then(|host| {
+        CONFIG
+            .get()
+            .and_then(|config| config.hosts.get(&host))
+            .cloned()
+    }) else {
+        return request;
+    };
+
+    let path = request.uri().path();
+    if SHARED_PATHS.iter().any(|shared| {
+        path.strip_prefix(shared)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }) {
+        return request;
+    }
+
+    let rewritten = match (&host_def.instance, path) {
+        (Some(instance), "/") => format!("/play/{}/{}/index", host_def.game, instance),
+        (Some(instance), path) => format!("/play/{}/{}{}", host_def.game, instance, path),
+        (None, "/") => format!("/play/{}", host_def.game),
+        (None, path) => format!("/play/{}{}", host_def.game, path),
+    };
+    let rewritten = match request.uri().query() {
+        Some(query) => format!("{}?{}", rewritten, query),
+        None => rewritten,
+    };
+
+    match rewritten.parse::<Uri>() {
+        Ok(uri) => {
+            debug!("Rewrite {} to {}", request.uri(), uri);
+            *request.uri_mut() = uri;
+        }
+        Err(err) => error!("Failed to rewrite {} for host: {}", request.uri(), err),
+    }
+    request
+}
+
+fn get_request_host(request: &Request) -> Option<String> {
+    let host = request.uri().host().map(str::to_string).or_else(|| {
+        request
+            .headers()
+            .get(HOST)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .rsplit_once(':')
+                    .map_or(value, |(host, _)| host)
+                    .to_string()
+            })
+    })?;
+    Some(host.to_ascii_lowercase())
+}
+
+/// Warns about hosts pointing at games or instances the registry does not know.
+pub fn check_hosts(hosts: &HashMap<String, HostDef>, registry: &GameRegistry) {
+    for (host, host_def) in hosts {
+        match registry.get(&host_def.game) {
+            None => warn!("Host {} maps to unknown game {}", host, host_def.game),
+            Some(game) => {
+                if let Some(instance) = &host_def.instance
+                    && game.get_instance(instance).is_none()
+                {
+                    warn!(
+                        "Host {} maps to unknown instance {} of game {}",
+                        host, instance, host_def.game
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use super::auth::{get_cookie, safe_next};
+use crate::foundation::audit::{Actor, AuditAction, AuditTarget};
+use crate::foundation::household::{HouseholdProfile, SELECTION_MAX_AGE_SECS};
+use crate::util::AppState;
+use askama::Template;
+use axum::extract::{Query, State};
+use axum::http::header::SET_COOKIE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Form, Router};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+pub const HOUSEHOLD_COOKIE: &str = "household_profile";
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/profiles",
+            get(handle_profiles_page).post(handle_profile_create),
+        )
+        .route("/profiles/select", post(handle_profile_select))
+        .route("/profiles/leave", post(handle_profile_leave))
+}
+
+#[derive(Template)]
+#[template(path = "profiles.html")]
+struct ProfilesTemplate {
+    profiles: Vec<HouseholdProfile>,
+    active: Option<String>,
+    next: String,
+    error: Option<String>,
+}
+
+fn render_profiles(
+    state: &AppState,
+    active: Option<&HouseholdProfile>,
+    next: &str,
+    error: Option<&str>,
+    status: StatusCode,
+) -> Response {
+    let template = ProfilesTemplate {
+        profiles: state.households.all(),
+        active: active.map(|profile| profile.name.clone()),
+        next: next.to_string(),
+        error: error.map(str::to_string),
+    };
+    match template.render() {
+        Ok(html) => (status, Html(html)).into_response(),
+        Err(err) => {
+            error!("Failed to render profiles page: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ProfilesQuery {
+    next: Option<String>,
+}
+
+async fn handle_profiles_page(
+    State(state): State<Arc<AppState>>,
+    profile: Option<Extension<HouseholdProfile>>,
+    Query(query): Query<ProfilesQuery>,
+) -> impl IntoResponse {
+    render_profiles(
+        &state,
+        profile.as_ref().map(|Extension(profile)| profile),
+        safe_next(query.next.as_deref()),
+        None,
+        StatusCode::OK,
+    )
+}
+
+#[derive(Deserialize)]
+struct ProfileCreateForm {
+    name: String,
+    #[serde(default)]
+    pin: Option<String>,
+    #[serde(default)]
+    color: Option<String>,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+async fn handle_profile_create(
+    State(state): State<Arc<AppState>>,
+    profile: Option<Extension<HouseholdProfile>>,
+    actor: Actor,
+    Form(form): Form<ProfileCreateForm>,
+) -> impl IntoResponse {
+    let next = safe_next(form.next.as_deref());
+    match state
+        .households
+        .add(&form.name, form.pin.as_deref(), form.color.as_deref())
+    {
+        Ok(_) => {
+            info!("Household profile created: {}", form.name.trim());
+            state.audit.record(
+                &actor,
+                AuditAction::HouseholdAdd,
+                AuditTarget {
+                    target: Some(form.name.trim()),
+                    ..Default::default()
+                },
+            );
+            Redirect::to(&format!(
+                "/profiles?{}",
+                serde_urlencoded::to_string([("next", next)]).unwrap_or_default()
+            ))
+            .into_response()
+        }
+        Err(err) => render_profiles(
+            &state,
+            profile.as_ref().map(|Extension(profile)| profile),
+            next,
+            Some(&err.to_string()),
+            StatusCode::BAD_REQUEST,
+        ),
+    }
+}
+
+#[derive(Deserialize)]
+struct ProfileSelectForm {
+    name: String,
+    #[serde(default)]
+    pin: Option<String>,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+async fn handle_profile_select(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    profile: Option<Extension<HouseholdProfile>>,
+    actor: Actor,
+    Form(form): Form<ProfileSelectForm>,
+) -> impl IntoResponse {
+    let next = safe_next(form.next.as_deref());
+    // The PIN hash is checked on a blocking thread, it takes a while on purpose.
+    let selected = {
+        let state = state.clone();
+        let (name, pin, client) = (form.name.clone(), form.pin.clone(), actor.ip.clone());
+        tokio::task::spawn_blocking(move || {
+            state
+                .households
+                .select(&name, pin.as_deref(), client.as_deref())
+        })
+        .await
+        .unwrap_or_else(|err| Err(err.into()))
+    };
+    match selected {
+        Ok(token) => {
+            if let Some(previous) = get_cookie(&headers, HOUSEHOLD_COOKIE)
+                && let Err(err) = state.households.deselect(previous)
+            {
+                error!("Failed to forget previous profile selection: {}", err);
+            }
+            info!("Household profile picked: {}", form.name);
+            state.audit.record(
+                &actor,
+                AuditAction::HouseholdSelect,
+                AuditTarget {
+                    target: Some(&form.name),
+                    ..Default::default()
+                },
+            );
+            let cookie = format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+                HOUSEHOLD_COOKIE, token, SELECTION_MAX_AGE_SECS
+            );
+            ([(SET_COOKIE, cookie)], Redirect::to(next)).into_response()
+        }
+        Err(err) => {
+            warn!("Failed to pick household profile {}: {}", form.name, err);
+            render_profiles(
+                &state,
+                profile.as_ref().map(|Extension(profile)| profile),
+                next,
+                Some(&err.to_string()),
+                StatusCode::UNAUTHORIZED,
+            )
+        }
+    }
+}
+
+async fn handle_profile_leave(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Some(token) = get_cookie(&headers, HOUSEHOLD_COOKIE)
+        && let Err(err) = state.households.deselect(token)
+    {
+        error!("Failed to forget profile selection: {}", err);
+    }
+
+    let cookie = format!(
+        "{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0",
+        HOUSEHOLD_COOKIE
+    );
+    ([(SET_COOKIE, cookie)], Redirect::to("/profiles")).into_response()
+}
@@ -1,8 +1,11 @@
+use crate::foundation::household::HouseholdProfile;
 use crate::foundation::registry::Registry;
 use crate::foundation::structure::InstanceInfo;
+use crate::foundation::user::Identity;
 use crate::util::AppState;
 use askama::Template;
-use axum::extract::State;
+use axum::Extension;
+use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::response::{Html, IntoResponse};
 use std::sync::Arc;
@@ -12,18 +15,63 @@ use tracing::error;
 #[template(path = "index.html")]
 struct IndexTemplate<'a> {
     instance_map: Vec<((String, String), Vec<&'a InstanceInfo>)>,
+    identity: Option<Identity>,
+    profile: Option<HouseholdProfile>,
+    has_profiles: bool,
 }
 
-pub async fn index_page(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let mut instance_map: Vec<((String, String), Vec<&InstanceInfo>)> = state
-        .registry
-        .all()
+pub async fn index_page(
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    render_index(
+        &state,
+        None,
+        identity.map(|Extension(identity)| identity),
+        profile.map(|Extension(profile)| profile),
+    )
+}
+
+/// The instance list of a single game, served at the root of a game's virtual host.
+pub async fn game_index_page(
+    Path(game_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    if state.registry.get(&game_id).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("no game found with id {}", game_id),
+        )
+            .into_response();
+    }
+    render_index(
+        &state,
+        Some(&game_id),
+        identity.map(|Extension(identity)| identity),
+        profile.map(|Extension(profile)| profile),
+    )
+}
+
+fn render_index(
+    state: &AppState,
+    game_filter: Option<&str>,
+    identity: Option<Identity>,
+    profile: Option<HouseholdProfile>,
+) -> axum::response::Response {
+    let games = state.registry.all();
+    let mut instance_map: Vec<((String, String), Vec<&InstanceInfo>)> = games
         .iter()
+        .filter(|(id, _)| game_filter.is_none_or(|game_id| game_id == id.as_str()))
+        .filter(|(_, game_info)| game_info.allows(identity.as_ref()))
         .map(|(id, game_info)| {
             let mut instances: Vec<&InstanceInfo> = game_info
                 .instances
-                .iter()
-                .map(|(_, instance_info)| instance_info)
+                .values()
+                .map(Arc::as_ref)
+                .filter(|instance| instance.access.allows(identity.as_ref()))
                 .collect();
             instances.sort_by(|a, b| a.id.cmp(&b.id));
 
@@ -35,7 +83,12 @@ pub async fn index_page(State(state): State<Arc<AppState>>) -> impl IntoResponse
         })
         .collect();
     instance_map.sort_by(|a, b| a.0.0.cmp(&b.0.0));
-    let template = IndexTemplate { instance_map };
+    let template = IndexTemplate {
+        instance_map,
+        identity,
+        profile,
+        has_profiles: !state.households.all().is_empty(),
+    };
 
     match template.render() {
         Ok(html) => Html(html).into_response(),
@@ -0,0 +1,363 @@
+use super::admin::get_managed_game;
+use super::game::InstanceStatus;
+use crate::foundation::audit::{Actor, AuditAction, AuditTarget};
+use crate::foundation::registry::{
+    INSTANCE_FORMATS, Registry, delete_instance, validate_instance, write_instance,
+};
+use crate::foundation::structure::{GameInfo, InstanceInfo};
+use crate::foundation::user::Identity;
+use crate::util::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{post, put};
+use axum::{Extension, Json, Router, middleware};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{error, info};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+/// Admin endpoints managing the instance files of a game, reading them is left to the public
+/// routes of [`super::game`], which answer admins with [`InstanceView`].
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/games/{game_id}/instances",
+            post(handle_instance_create),
+        )
+        .route(
+            "/api/games/{game_id}/instances/{instance_id}",
+            put(handle_instance_update).delete(handle_instance_delete),
+        )
+        .route_layer(middleware::from_fn(super::auth::require_admin))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(handle_instance_create, handle_instance_update, handle_instance_delete))]
+pub(super) struct InstanceApi;
+
+/// An instance as stored in its file, along with its status.
+#[derive(Serialize, ToSchema)]
+pub(super) struct InstanceView<'a> {
+    #[serde(flatten)]
+    instance: &'a InstanceInfo,
+    /// Format of the file the instance is stored in.
+    format: Option<&'a str>,
+    #[serde(flatten)]
+    status: InstanceStatus,
+}
+
+impl<'a> InstanceView<'a> {
+    pub(super) fn of(game: &GameInfo, instance: &'a InstanceInfo) -> Self {
+        InstanceView {
+            instance,
+            format: instance.get_format(),
+            status: InstanceStatus::of(game, instance),
+        }
+    }
+}
+
+#[derive(Deserialize, Default, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+struct InstanceFormatQuery {
+    /// `json`, `toml` or `yaml`, defaults to the current format or `json`.
+    format: Option<String>,
+}
+
+fn check_format(format: Option<&str>) -> Result<Option<&str>, (StatusCode, String)> {
+    match format {
+        Some(format) if !INSTANCE_FORMATS.contains(&format) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unsupported format: {}", format),
+        )),
+        format => Ok(format),
+    }
+}
+
+/// Writes an instance, replacing `replacing`, and responds with what was written. The instance
+/// file system is rebuilt while writing, so it runs on a blocking thread.
+async fn put_instance(
+    state: Arc<AppState>,
+    game_id: String,
+    instance: InstanceInfo,
+    format: String,
+    replacing: Option<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let instance_id = instance.id.clone();
+    let written = {
+        let state = state.clone();
+        let game_id = game_id.clone();
+        let instance_id = instance_id.clone();
+        tokio::task::spawn_blocking(move || {
+            state.registry.update(&game_id, |game| {
+                let replacing = replacing.as_deref();
+                if replacing != Some(instance_id.as_str())
+                    && game.instances.contains_key(&instance_id)
+                {
+                    return Err((
+                        StatusCode::CONFLICT,
+                        format!("Instance '{}' already exists", instance_id),
+                    ));
+                }
+                validate_instance(game, &instance, replacing)
+                    .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+                write_instance(game, instance, &format, replacing).map_err(|err| {
+                    error!(
+                        "Failed to write instance {}/{}: {}",
+                        game_id, instance_id, err
+                    );
+                    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                })
+            })
+        })
+        .await
+    };
+    match written {
+        Ok(Some(result)) => result?,
+        Ok(None) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("no game found with id {}", game_id),
+            ));
+        }
+        Err(err) => {
+            error!(
+                "Failed to write instance {}/{}: {}",
+                game_id, instance_id, err
+            );
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string()));
+        }
+    }
+
+    let game = state.registry.get(&game_id);
+    let (game, instance) = game
+        .as_ref()
+        .and_then(|game| Some((game, game.instances.get(&instance_id)?)))
+        .ok_or_else(|| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                String::from("Instance vanished after writing it"),
+            )
+        })?;
+    Ok(Json(InstanceView::of(game, instance)).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_id}/instances",
+    tag = "admin",
+    params(("game_id", description = "Game id"), InstanceFormatQuery),
+    request_body = InstanceInfo,
+    responses(
+        (status = 201, description = "The written instance", body = InstanceView),
+        (status = 400, description = "Invalid instance or format"),
+        (status = 404, description = "Unknown game"),
+        (status = 409, description = "The instance already exists")
+    )
+)]
+async fn handle_instance_create(
+    Path(game_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    Query(query): Query<InstanceFormatQuery>,
+    Json(instance): Json<InstanceInfo>,
+) -> impl IntoResponse {
+    if let Err(err) = get_managed_game(&state, &identity, &game_id) {
+        return err.into_response();
+    }
+    let format = match check_format(query.format.as_deref()) {
+        Ok(format) => format.unwrap_or("json").to_string(),
+        Err(err) => return err.into_response(),
+    };
+
+    let instance_id = instance.id.clone();
+    match put_instance(state.clone(), game_id.clone(), instance, format, None).await {
+        Ok(response) => {
+            info!(
+                "Instance {}/{} created by {}",
+                game_id, instance_id, identity.name
+            );
+            state.audit.record(
+                &actor,
+                AuditAction::InstanceCreate,
+                AuditTarget {
+                    game: Some(&game_id),
+                    instance: Some(&instance_id),
+                    ..Default::default()
+                },
+            );
+            (StatusCode::CREATED, response).into_response()
+        }
+        Err(err) => err.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/games/{game_id}/instances/{instance_id}",
+    tag = "admin",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id, a different id in the body renames it"),
+        InstanceFormatQuery,
+    ),
+    request_body = InstanceInfo,
+    responses(
+        (status = 200, description = "The written instance", body = InstanceView),
+        (status = 400, description = "Invalid instance or format"),
+        (status = 404, description = "Unknown game or instance"),
+        (status = 409, description = "The new id is taken")
+    )
+)]
+async fn handle_instance_update(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    Query(query): Query<InstanceFormatQuery>,
+    Json(body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    // Whether the body sets these is lost once deserialized, and a rename keeps them otherwise.
+    let has_save_namespace = body.get("save_namespace").is_some();
+    let has_aliases = body.get("aliases").is_some();
+    let mut instance: InstanceInfo = match serde_json::from_value(body) {
+        Ok(instance) => instance,
+        Err(err) => return (StatusCode::UNPROCESSABLE_ENTITY, err.to_string()).into_response(),
+    };
+    let game = match get_managed_game(&state, &identity, &game_id) {
+        Ok(game) => game,
+        Err(err) => return err.into_response(),
+    };
+    let Some(current) = game.instances.get(&instance_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("no instance found with id {}", instance_id),
+        )
+            .into_response();
+    };
+    let format = match check_format(query.format.as_deref()) {
+        Ok(format) => format
+            .or(current.get_format())
+            .unwrap_or("json")
+            .to_string(),
+        Err(err) => return err.into_response(),
+    };
+    // A renamed instance keeps its saves and its old links unless told otherwise.
+    if instance.id != instance_id {
+        if !has_save_namespace {
+            instance.save_namespace = Some(current.get_save_namespace().to_string());
+        }
+        if !has_aliases {
+            instance.aliases = current.aliases.clone();
+            if !instance.aliases.contains(&instance_id) {
+                instance.aliases.push(instance_id.clone());
+            }
+        }
+    }
+
+    let new_id = instance.id.clone();
+    let replacing = Some(instance_id.clone());
+    match put_instance(state.clone(), game_id.clone(), instance, format, replacing).await {
+        Ok(response) => {
+            info!(
+                "Instance {}/{} updated by {}",
+                game_id, instance_id, identity.name
+            );
+            if new_id != instance_id {
+                info!("Instance {}/{} renamed to {}", game_id, instance_id, new_id);
+            }
+            state.audit.record(
+                &actor,
+                AuditAction::InstanceUpdate,
+                AuditTarget {
+                    game: Some(&game_id),
+                    instance: Some(&instance_id),
+                    target: Some(&new_id),
+                },
+            );
+            response
+        }
+        Err(err) => err.into_response(),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/games/{game_id}/instances/{instance_id}",
+    tag = "admin",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id"),
+    ),
+    responses(
+        (status = 204, description = "The instance file was deleted"),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
+async fn handle_instance_delete(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+) -> impl IntoResponse {
+    if let Err(err) = get_managed_game(&state, &identity, &game_id) {
+        return err.into_response();
+    }
+
+    let result = {
+        let state = state.clone();
+        let game_id = game_id.clone();
+        let instance_id = instance_id.clone();
+        tokio::task::spawn_blocking(move || {
+            state.registry.update(&game_id, |game| {
+                if !game.instances.contains_key(&instance_id) {
+                    return Err((
+                        StatusCode::NOT_FOUND,
+                        format!("no instance found with id {}", instance_id),
+                    ));
+                }
+                delete_instance(game, &instance_id).map_err(|err| {
+                    error!(
+                        "Failed to delete instance {}/{}: {}",
+                        game_id, instance_id, err
+                    );
+                    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                })
+            })
+        })
+        .await
+    };
+    match result {
+        Ok(Some(Ok(()))) => {
+            info!(
+                "Instance {}/{} deleted by {}",
+                game_id, instance_id, identity.name
+            );
+            state.audit.record(
+                &actor,
+                AuditAction::InstanceDelete,
+                AuditTarget {
+                    game: Some(&game_id),
+                    instance: Some(&instance_id),
+                    ..Default::default()
+                },
+            );
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(Some(Err(err))) => err.into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            format!("no game found with id {}", game_id),
+        )
+            .into_response(),
+        Err(err) => {
+            error!(
+                "Failed to delete instance {}/{}: {}",
+                game_id, instance_id, err
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+    }
+}
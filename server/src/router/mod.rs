@@ -9,17 +9,55 @@ use axum::routing::get;
 use lazy_static::lazy_static;
 use std::sync::Arc;
 
+mod admin;
+mod audit;
+mod auth;
+mod game;
+mod gc;
+mod host;
+mod household;
 mod index;
+mod instance;
+mod openapi;
 mod play;
 mod repo;
 mod save;
+mod share;
+mod stats;
+mod upload;
 
-pub fn get_router() -> Router<Arc<AppState>> {
+pub use auth::authenticate;
+use axum::middleware;
+pub use host::{check_hosts, rewrite_host_request};
+
+pub fn get_router(state: Arc<AppState>) -> Router<Arc<AppState>> {
     Router::new()
         .route("/", get(index::index_page))
         .route("/favicon.ico", get(get_icon))
-        .nest("/play", play::routes())
-        .nest("/repo", repo::routes())
+        .nest(
+            "/play",
+            play::routes().route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth::authorize,
+            )),
+        )
+        .nest(
+            "/repo",
+            repo::routes().route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth::authorize,
+            )),
+        )
+        .nest("/share", share::routes())
+        .merge(auth::routes())
+        .merge(household::routes())
+        .merge(admin::routes())
+        .merge(game::routes(state))
+        .merge(instance::routes())
+        .merge(upload::routes())
+        .merge(gc::routes())
+        .merge(stats::routes())
+        .merge(openapi::routes())
 }
 
 const ICON: &[u8] = include_bytes!("../../res/favicon.ico");
@@ -29,7 +67,7 @@ lazy_static! {
 }
 
 async fn get_icon(headers: HeaderMap) -> impl IntoResponse {
-    if let Some(response) = etag_check(&ICON.to_vec(), &headers) {
+    if let Some(response) = etag_check(ICON, &headers) {
         return response;
     }
 
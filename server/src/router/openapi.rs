@@ -0,0 +1,66 @@
+use super::auth::SESSION_COOKIE;
+use super::{admin, game, gc, instance, play, repo, share, stats, upload};
+use crate::util::AppState;
+use axum::Router;
+use std::sync::Arc;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Serves the OpenAPI document built from the handler annotations at `/api/openapi.json`, and a
+/// viewer for it at `/api/docs`.
+pub fn routes() -> Router<Arc<AppState>> {
+    SwaggerUi::new("/api/docs")
+        .url("/api/openapi.json", get_openapi())
+        .into()
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Degrees of Management",
+        description = "JSON endpoints of the server, pages and game files are left out.",
+        license(name = "GPL-3.0", identifier = "GPL-3.0-only")
+    ),
+    modifiers(&SecuritySchemes),
+    // Anonymous access works for games without access control.
+    security((), ("bearer" = []), ("session" = [])),
+    tags(
+        (name = "games", description = "Games and instances the caller may access"),
+        (name = "play", description = "Files the mod loader of a running game requests"),
+        (name = "save-sync", description = "Saves, automatic saves and profile values of an instance"),
+        (name = "admin", description = "API tokens, the audit log and instance files"),
+        (name = "content", description = "Indexes, layers, mods and releases of a game"),
+    )
+)]
+struct ApiDoc;
+
+/// Each router module documents its own handlers, they are merged into one document here.
+fn get_openapi() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+        .merge_from(game::GameApi::openapi())
+        .merge_from(play::PlayApi::openapi())
+        .merge_from(repo::RepoApi::openapi())
+        .merge_from(share::ShareApi::openapi())
+        .merge_from(admin::AdminApi::openapi())
+        .merge_from(instance::InstanceApi::openapi())
+        .merge_from(upload::UploadApi::openapi())
+        .merge_from(gc::GcApi::openapi())
+        .merge_from(stats::StatsApi::openapi())
+}
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(SESSION_COOKIE))),
+        );
+    }
+}
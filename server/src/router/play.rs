@@ -1,21 +1,30 @@
 use crate::constants::CACHE_HEADER;
+use crate::foundation::save::{SAVE_ARCHIVE_MAX_SIZE, SAVE_UPLOAD_MAX_SIZE};
+use crate::foundation::structure::{GameInfo, InstanceInfo};
 use crate::router::repo::SAVE_SYNC_INTEGRATION_MOD_ID;
-use crate::router::save;
+use crate::router::{index, save};
 use crate::util::AppState;
 use crate::util::extract::{extract_game_instance, extract_index};
-use crate::util::file::{etag_check, etag_hash};
-use axum::extract::{Path, State};
+use crate::util::file::{encode_path_segment, etag_check, etag_hash};
+use crate::util::inject::inject_head_script;
+use axum::extract::{DefaultBodyLimit, Path, State};
 use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG};
-use axum::http::{HeaderMap, StatusCode};
-use axum::response::{Html, IntoResponse};
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::response::{Html, IntoResponse, Redirect};
 use axum::routing::{get, post};
 use axum::{Json, Router};
 use std::fs;
 use std::sync::Arc;
 use tracing::{error, info, warn};
+use utoipa::OpenApi;
+
+const STORAGE_BACKUP_SCRIPT: &str = include_str!("../../res/storage-backup.js");
+const STORAGE_ISOLATION_SCRIPT: &str = include_str!("../../res/storage-isolation.js");
+const STORAGE_PREFIX_PLACEHOLDER: &str = "__STORAGE_PREFIX__";
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
+        .route("/{game_id}", get(index::game_index_page))
         .route("/{game_id}/{instance_id}/index", get(handle_play_index))
         .route(
             "/{game_id}/{instance_id}/modList.json",
@@ -29,25 +38,111 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/{game_id}/{instance_id}/save-sync/access",
             post(save::handle_save_upload),
         )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/config",
+            get(save::handle_save_sync_config),
+        )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/auto",
+            get(save::handle_auto_save_list)
+                .post(save::handle_auto_save_upload)
+                .layer(DefaultBodyLimit::max(SAVE_UPLOAD_MAX_SIZE)),
+        )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/auto/{save_id}",
+            get(save::handle_auto_save_get),
+        )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/auto/{save_id}/keep",
+            post(save::handle_auto_save_keep),
+        )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/events",
+            get(save::handle_save_events),
+        )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/export",
+            get(save::handle_save_export),
+        )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/import",
+            post(save::handle_save_import).layer(DefaultBodyLimit::max(SAVE_ARCHIVE_MAX_SIZE)),
+        )
         .route(
             "/{game_id}/{instance_id}/save-sync/access/{save_id}",
-            get(save::handle_save_get).delete(save::handle_save_del),
+            get(save::handle_save_get)
+                .patch(save::handle_save_patch)
+                .delete(save::handle_save_del),
+        )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/access/{save_id}/share",
+            post(save::handle_save_share),
+        )
+        .route(
+            "/{game_id}/{instance_id}/storage-backup",
+            get(save::handle_storage_backup_get)
+                .put(save::handle_storage_backup_put)
+                .layer(DefaultBodyLimit::max(SAVE_UPLOAD_MAX_SIZE)),
+        )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/profile/{alias}",
+            get(save::handle_profile_list),
+        )
+        .route(
+            "/{game_id}/{instance_id}/save-sync/profile/{alias}/{key}",
+            get(save::handle_profile_get)
+                .put(save::handle_profile_put)
+                .delete(save::handle_profile_del)
+                .layer(DefaultBodyLimit::max(SAVE_UPLOAD_MAX_SIZE)),
         )
         .route("/{game_id}/{instance_id}/{*path}", get(handle_other_file))
 }
 
+/// The JSON endpoints of [`routes`], the pages and game files are left out.
+#[derive(OpenApi)]
+#[openapi(paths(
+    handle_mod_list,
+    save::handle_save_list,
+    save::handle_save_upload,
+    save::handle_save_get,
+    save::handle_save_patch,
+    save::handle_save_del,
+    save::handle_save_share,
+    save::handle_save_sync_config,
+    save::handle_auto_save_list,
+    save::handle_auto_save_upload,
+    save::handle_auto_save_get,
+    save::handle_auto_save_keep,
+    save::handle_save_events,
+    save::handle_save_export,
+    save::handle_save_import,
+    save::handle_storage_backup_get,
+    save::handle_storage_backup_put,
+    save::handle_profile_list,
+    save::handle_profile_get,
+    save::handle_profile_put,
+    save::handle_profile_del,
+))]
+pub(super) struct PlayApi;
+
 async fn handle_play_index(
     Path((game_id, instance_id)): Path<(String, String)>,
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
+    uri: Uri,
 ) -> impl IntoResponse {
     let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
         Ok(result) => result,
         Err(response) => return response.into_response(),
     };
 
+    if instance.id != instance_id {
+        let target = format!("/play/{}/{}/index", game_id, instance.id);
+        return Redirect::temporary(&with_query(target, &uri)).into_response();
+    }
+
     let index_id = &instance.index;
-    let index_info = match extract_index(game, index_id) {
+    let index_info = match extract_index(&game, index_id) {
         Ok(result) => result,
         Err(response) => return response.into_response(),
     };
@@ -56,6 +151,8 @@ async fn handle_play_index(
 
     match fs::read(&index_info.path) {
         Ok(content) => {
+            let content = inject_storage_scripts(&game, &instance, content);
+
             if let Some(response) = etag_check(&content, &headers) {
                 return response;
             }
@@ -86,6 +183,42 @@ async fn handle_play_index(
     }
 }
 
+/// Adds the browser storage scripts of the game to its index. Each injection lands at the top of
+/// `<head>`, so the isolation shim is injected last to run before anything touches the storage.
+fn inject_storage_scripts(game: &GameInfo, instance: &InstanceInfo, content: Vec<u8>) -> Vec<u8> {
+    let game_def = &game.game_def;
+    if !game_def.is_use_storage_backup() && !game_def.storage_isolation {
+        return content;
+    }
+
+    let mut html = content;
+    if game_def.is_use_storage_backup() {
+        html = inject_head_script(&html, STORAGE_BACKUP_SCRIPT);
+    }
+    if game_def.storage_isolation {
+        let prefix = format!("{}/{}/", game.id, instance.get_save_namespace());
+        let script = STORAGE_ISOLATION_SCRIPT.replace(
+            STORAGE_PREFIX_PLACEHOLDER,
+            &serde_json::Value::String(prefix).to_string(),
+        );
+        html = inject_head_script(&html, &script);
+    }
+    html
+}
+
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/modList.json",
+    tag = "play",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    responses(
+        (status = 200, description = "URLs of the mods the instance loads", body = Vec<String>),
+        (status = 404, description = "Unknown game or instance, or the game does not use mods")
+    )
+)]
 async fn handle_mod_list(
     Path((game_id, instance_id)): Path<(String, String)>,
     State(state): State<Arc<AppState>>,
@@ -106,7 +239,7 @@ async fn handle_mod_list(
     let mut mods: Vec<String> = instance
         .mods
         .iter()
-        .filter(|mod_id| game.mods.get(*mod_id).is_some())
+        .filter(|mod_id| game.mods.contains_key(*mod_id))
         .map(|mod_id| format!("/repo/mod/{game_id}/{mod_id}"))
         .collect();
 
@@ -119,16 +252,32 @@ async fn handle_mod_list(
     Json(mods).into_response()
 }
 
+/// Alias redirects keep the query string, the game may read options from it.
+fn with_query(path: String, uri: &Uri) -> String {
+    match uri.query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path,
+    }
+}
+
 async fn handle_other_file(
     Path((game_id, instance_id, path)): Path<(String, String, String)>,
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
+    uri: Uri,
 ) -> impl IntoResponse {
     let (_, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
         Ok(result) => result,
         Err(response) => return response.into_response(),
     };
 
+    if instance.id != instance_id {
+        // The path arrives decoded, encode it again so the redirect names the same file.
+        let path: Vec<String> = path.split('/').map(encode_path_segment).collect();
+        let target = format!("/play/{}/{}/{}", game_id, instance.id, path.join("/"));
+        return Redirect::temporary(&with_query(target, &uri)).into_response();
+    }
+
     let instance_fs = match instance.get_fs() {
         Some(fs) => fs,
         None => {
@@ -12,6 +12,7 @@ use axum::routing::get;
 use lazy_static::lazy_static;
 use std::sync::Arc;
 use tracing::error;
+use utoipa::OpenApi;
 
 pub const SAVE_SYNC_INTEGRATION_MOD_ID: &str = "save-sync-integration";
 const SAVE_SYNC_INTEGRATION_INTERNAL: &[u8] =
@@ -28,6 +29,25 @@ pub fn routes() -> Router<Arc<AppState>> {
     Router::new().route("/mod/{game_id}/{mod_id}", get(handle_mod_file))
 }
 
+#[derive(OpenApi)]
+#[openapi(paths(handle_mod_file))]
+pub(super) struct RepoApi;
+
+#[utoipa::path(
+    get,
+    path = "/repo/mod/{game_id}/{mod_id}",
+    tag = "play",
+    params(
+        ("game_id", description = "Game id"),
+        ("mod_id", description = "Mod id"),
+    ),
+    responses(
+        (status = 200, description = "The mod zip", body = Vec<u8>, content_type = "application/zip"),
+        (status = 304, description = "The cached mod is still current"),
+        (status = 400, description = "The game does not use mods"),
+        (status = 404, description = "Unknown game or mod")
+    )
+)]
 async fn handle_mod_file(
     Path((game_id, mod_id)): Path<(String, String)>,
     State(state): State<Arc<AppState>>,
@@ -43,19 +63,18 @@ async fn handle_mod_file(
     }
 
     if mod_id == SAVE_SYNC_INTEGRATION_MOD_ID {
-        if let Some(if_not_match) = headers.get(IF_NONE_MATCH) {
-            if let Ok(cli_tag) = if_not_match.to_str() {
-                if cli_tag == SAVE_SYNC_INTEGRATION_ETAG.as_str() {
-                    return (
-                        StatusCode::NOT_MODIFIED,
-                        [
-                            (CACHE_CONTROL, CACHE_HEADER),
-                            (ETAG, SAVE_SYNC_INTEGRATION_ETAG.as_str()),
-                        ],
-                    )
-                        .into_response();
-                }
-            }
+        if let Some(if_not_match) = headers.get(IF_NONE_MATCH)
+            && let Ok(cli_tag) = if_not_match.to_str()
+            && cli_tag == SAVE_SYNC_INTEGRATION_ETAG.as_str()
+        {
+            return (
+                StatusCode::NOT_MODIFIED,
+                [
+                    (CACHE_CONTROL, CACHE_HEADER),
+                    (ETAG, SAVE_SYNC_INTEGRATION_ETAG.as_str()),
+                ],
+            )
+                .into_response();
         }
 
         return (
@@ -106,3 +125,61 @@ async fn handle_mod_file(
     )
         .into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::SAVE_SYNC_INTEGRATION_INTERNAL;
+    use std::fs;
+    use std::io::{Cursor, Read};
+    use std::path::PathBuf;
+    use zip::ZipArchive;
+
+    const BOOT_FILE_LISTS: [&str; 5] = [
+        "styleFileList",
+        "scriptFileList",
+        "tweeFileList",
+        "imgFileList",
+        "additionFile",
+    ];
+
+    /// The embedded mod is packed from `mod/` by hand, this catches a source change without a repack.
+    #[test]
+    fn embedded_mod_matches_sources() {
+        let mod_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../mod");
+        let mut archive = ZipArchive::new(Cursor::new(SAVE_SYNC_INTEGRATION_INTERNAL)).unwrap();
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            if !file.is_file() {
+                continue;
+            }
+            let name = file.name().to_string();
+            let mut packed = Vec::new();
+            file.read_to_end(&mut packed).unwrap();
+            let source = fs::read(mod_dir.join(&name))
+                .unwrap_or_else(|err| panic!("mod/{} is packed but unreadable: {}", name, err));
+
+            // The packer reformats boot.json, only its content has to match.
+            if name == "boot.json" {
+                let packed: serde_json::Value = serde_json::from_slice(&packed).unwrap();
+                let source: serde_json::Value = serde_json::from_slice(&source).unwrap();
+                assert_eq!(packed, source, "boot.json differs from mod/boot.json");
+            } else {
+                assert!(packed == source, "{} differs from mod/{}", name, name);
+            }
+        }
+
+        let boot: serde_json::Value =
+            serde_json::from_slice(&fs::read(mod_dir.join("boot.json")).unwrap()).unwrap();
+        for list in BOOT_FILE_LISTS {
+            for name in boot[list].as_array().into_iter().flatten() {
+                let name = name.as_str().unwrap();
+                assert!(
+                    archive.by_name(name).is_ok(),
+                    "{} is listed in boot.json but not packed",
+                    name
+                );
+            }
+        }
+    }
+}
@@ -1,41 +1,262 @@
+use crate::foundation::audit::{Actor, AuditAction, AuditTarget};
+use crate::foundation::event::{SaveEvent, SaveEventKind};
+use crate::foundation::household::HouseholdProfile;
+use crate::foundation::save::{
+    AutoSaveBatch, AutoSaveOutcome, AutoSaveResult, SaveArchiveManifest, SaveCode, SaveEntry,
+    SaveImportReport, SaveMeta, SaveMetaPatch, SaveWriteResult, build_save_archive, copy_save,
+    del_profile_value, del_save_content, get_auto_save_dir, get_owner_dir, get_profile_dir,
+    get_profile_value, get_save_alias, get_save_content, get_storage_backup, import_save_archive,
+    iter_save_list, list_profile_keys, list_save_entries, read_save_meta, save_exists,
+    write_auto_save, write_profile_value, write_save_content, write_save_meta,
+    write_storage_backup,
+};
+use crate::foundation::share::SHARE_MAX_EXPIRES_IN_HOURS;
+use crate::foundation::structure::InstanceInfo;
+use crate::foundation::upload::Staged;
+use crate::foundation::user::Identity;
 use crate::util::AppState;
-use crate::util::extract::extract_game;
-use anyhow::Result;
-use axum::Json;
-use axum::extract::{Path, State};
+use crate::util::extract::extract_game_instance;
+use crate::util::file::{is_valid_path_segment, stream_file_body};
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE};
 use axum::response::IntoResponse;
-use chrono::Local;
-use serde::Deserialize;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::{Extension, Json};
+use chrono::{Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
 use std::fs;
-use std::io::Write;
-use std::path::PathBuf;
 use std::sync::Arc;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::BroadcastStream;
 use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
 
-const SAVE_FILE_EXTENSION: &str = "save";
+/// Records an operation on a save or profile value of an instance in the audit log.
+fn audit_save(
+    state: &AppState,
+    actor: &Actor,
+    action: AuditAction,
+    game_id: &str,
+    instance: &InstanceInfo,
+    target: &str,
+) {
+    state.audit.record(
+        actor,
+        action,
+        AuditTarget {
+            game: Some(game_id),
+            instance: Some(&instance.id),
+            target: Some(target),
+        },
+    );
+}
+
+fn get_identity(identity: &Option<Extension<Identity>>) -> Option<&Identity> {
+    identity.as_ref().map(|Extension(identity)| identity)
+}
+
+fn get_profile(profile: &Option<Extension<HouseholdProfile>>) -> Option<&HouseholdProfile> {
+    profile.as_ref().map(|Extension(profile)| profile)
+}
+
+/// Saves and profile values of a household profile with a PIN are kept to browsers which picked
+/// that profile, admins still reach them to back them up.
+fn is_alias_unlocked(
+    state: &AppState,
+    identity: Option<&Identity>,
+    profile: Option<&HouseholdProfile>,
+    alias: &str,
+) -> bool {
+    identity.is_some_and(|identity| identity.admin)
+        || profile.is_some_and(|profile| profile.name == alias)
+        || !state.households.has_pin(alias)
+}
+
+/// The alias whose profile values a request works on, a picked household profile always works on
+/// its own whatever alias the path names.
+fn get_profile_alias(
+    state: &AppState,
+    identity: &Option<Extension<Identity>>,
+    profile: &Option<Extension<HouseholdProfile>>,
+    alias: String,
+) -> Result<String, (StatusCode, &'static str)> {
+    let alias = get_profile(profile).map_or(alias, |profile| profile.name.clone());
+    if !is_valid_path_segment(&alias) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid alias"));
+    }
+    if !is_alias_unlocked(state, get_identity(identity), get_profile(profile), &alias) {
+        return Err((StatusCode::FORBIDDEN, "Pick the household profile first"));
+    }
+    Ok(alias)
+}
+
+/// The name recorded as owner of saves written by a request.
+fn get_owner(identity: &Option<Extension<Identity>>) -> Option<&str> {
+    get_identity(identity).map(|identity| identity.name.as_str())
+}
+
+/// Saves hidden from the request are answered as missing so their ids do not leak, saves of other
+/// users shared with the instance may be read but not changed.
+fn check_save_access(
+    state: &AppState,
+    save_dir: &std::path::Path,
+    save_id: &str,
+    identity: &Option<Extension<Identity>>,
+    profile: &Option<Extension<HouseholdProfile>>,
+    modify: bool,
+) -> Result<(), (StatusCode, &'static str)> {
+    let meta = read_save_meta(save_dir, save_id);
+    if !meta.is_visible_to(get_identity(identity))
+        || !is_alias_unlocked(
+            state,
+            get_identity(identity),
+            get_profile(profile),
+            get_save_alias(save_id),
+        )
+    {
+        return Err((StatusCode::NOT_FOUND, "Save not found"));
+    }
+    if modify && !meta.is_owned_by(get_identity(identity)) {
+        return Err((StatusCode::FORBIDDEN, "Only the owner may change this save"));
+    }
+    Ok(())
+}
 
+#[derive(Deserialize, Default, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+pub struct SaveListQuery {
+    /// Only saves carrying this tag.
+    tag: Option<String>,
+    /// Only pinned, or only unpinned saves.
+    pinned: Option<bool>,
+    /// Case-insensitive search in save id and note.
+    q: Option<String>,
+    /// Return save entries with their metadata instead of plain ids.
+    detail: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/save-sync/list",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        SaveListQuery,
+    ),
+    responses(
+        (status = 200, description = "Save ids, or save entries with `detail`", body = Vec<SaveEntry>),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
 pub async fn handle_save_list(
     Path((game_id, instance_id)): Path<(String, String)>,
     State(state): State<Arc<AppState>>,
+    Query(query): Query<SaveListQuery>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
 ) -> impl IntoResponse {
-    let game = match extract_game(&state, &game_id) {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
         Ok(result) => result,
         Err(response) => return response.into_response(),
     };
-    Json(iter_save_list(&game.get_save_path_append(&instance_id))).into_response()
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+
+    let search = query.q.as_ref().map(|q| q.to_lowercase());
+    let entries: Vec<_> = list_save_entries(&save_dir)
+        .into_iter()
+        .filter(|entry| entry.meta.is_visible_to(get_identity(&identity)))
+        .filter(|entry| {
+            profile
+                .as_ref()
+                .is_none_or(|Extension(profile)| get_save_alias(&entry.id) == profile.name)
+        })
+        .filter(|entry| {
+            is_alias_unlocked(
+                &state,
+                get_identity(&identity),
+                get_profile(&profile),
+                get_save_alias(&entry.id),
+            )
+        })
+        .filter(|entry| {
+            query
+                .tag
+                .as_ref()
+                .is_none_or(|tag| entry.meta.tags.contains(tag))
+        })
+        .filter(|entry| {
+            query
+                .pinned
+                .is_none_or(|pinned| entry.meta.pinned == pinned)
+        })
+        .filter(|entry| {
+            search.as_ref().is_none_or(|search| {
+                entry.id.to_lowercase().contains(search)
+                    || entry
+                        .meta
+                        .note
+                        .as_ref()
+                        .is_some_and(|note| note.to_lowercase().contains(search))
+            })
+        })
+        .collect();
+
+    if query.detail {
+        Json(entries).into_response()
+    } else {
+        Json(
+            entries
+                .into_iter()
+                .map(|entry| entry.id)
+                .collect::<Vec<_>>(),
+        )
+        .into_response()
+    }
 }
 
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/save-sync/access/{save_id}",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("save_id", description = "Save id"),
+    ),
+    responses(
+        (status = 200, description = "The save code", body = String, content_type = "text/plain"),
+        (status = 400, description = "Invalid save id"),
+        (status = 403, description = "The save is private"),
+        (status = 404, description = "Unknown game, instance or save")
+    )
+)]
 pub async fn handle_save_get(
     Path((game_id, instance_id, save_id)): Path<(String, String, String)>,
     State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
 ) -> impl IntoResponse {
-    let game = match extract_game(&state, &game_id) {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
         Ok(result) => result,
         Err(response) => return response.into_response(),
     };
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+
+    if !is_valid_path_segment(&save_id) {
+        return (StatusCode::BAD_REQUEST, "Invalid save id").into_response();
+    }
+    if let Err(response) =
+        check_save_access(&state, &save_dir, &save_id, &identity, &profile, false)
+    {
+        return response.into_response();
+    }
 
-    let content = match get_save_content(&game.get_save_path_append(&instance_id), &save_id) {
+    let content = match get_save_content(&save_dir, &save_id) {
         Some(content) => content,
         None => {
             return StatusCode::NOT_FOUND.into_response();
@@ -47,40 +268,339 @@ pub async fn handle_save_get(
     content.into_response()
 }
 
+#[utoipa::path(
+    delete,
+    path = "/play/{game_id}/{instance_id}/save-sync/access/{save_id}",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("save_id", description = "Save id"),
+    ),
+    responses(
+        (status = 200, description = "The save was deleted", body = String, content_type = "text/plain"),
+        (status = 400, description = "Invalid save id"),
+        (status = 403, description = "The save belongs to someone else"),
+        (status = 409, description = "The save is pinned")
+    )
+)]
 pub async fn handle_save_del(
     Path((game_id, instance_id, save_id)): Path<(String, String, String)>,
     State(state): State<Arc<AppState>>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
 ) -> impl IntoResponse {
-    let game = match extract_game(&state, &game_id) {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
         Ok(result) => result,
         Err(response) => return response.into_response(),
     };
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
 
-    del_save_content(&game.get_save_path_append(&instance_id), &save_id);
+    if !is_valid_path_segment(&save_id) {
+        return (StatusCode::BAD_REQUEST, "Invalid save id").into_response();
+    }
+    if let Err(response) = check_save_access(&state, &save_dir, &save_id, &identity, &profile, true)
+    {
+        return response.into_response();
+    }
+
+    let meta = read_save_meta(&save_dir, &save_id);
+    if meta.pinned {
+        return (
+            StatusCode::CONFLICT,
+            format!("Save {save_id} is pinned, unpin it before deleting"),
+        )
+            .into_response();
+    }
+
+    if del_save_content(&save_dir, &save_id) {
+        state.events.publish(
+            SaveEventKind::Deleted,
+            &game_id,
+            instance.get_save_namespace(),
+            &save_id,
+            &meta,
+        );
+        audit_save(
+            &state,
+            &actor,
+            AuditAction::SaveDelete,
+            &game_id,
+            &instance,
+            &save_id,
+        );
+    }
 
     info!("Delete save file: {}-{}", game_id, instance_id);
     format!("Successfully deleted {save_id}").into_response()
 }
 
+#[utoipa::path(
+    patch,
+    path = "/play/{game_id}/{instance_id}/save-sync/access/{save_id}",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("save_id", description = "Save id"),
+    ),
+    request_body = SaveMetaPatch,
+    responses(
+        (status = 200, description = "The updated metadata", body = SaveMeta),
+        (status = 400, description = "Invalid save id"),
+        (status = 403, description = "The save belongs to someone else"),
+        (status = 404, description = "Unknown game, instance or save")
+    )
+)]
+pub async fn handle_save_patch(
+    Path((game_id, instance_id, save_id)): Path<(String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+    Json(patch): Json<SaveMetaPatch>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+
+    if !is_valid_path_segment(&save_id) {
+        return (StatusCode::BAD_REQUEST, "Invalid save id").into_response();
+    }
+    if !save_exists(&save_dir, &save_id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if let Err(response) = check_save_access(&state, &save_dir, &save_id, &identity, &profile, true)
+    {
+        return response.into_response();
+    }
+
+    let mut meta = read_save_meta(&save_dir, &save_id);
+    meta.apply(patch);
+
+    match write_save_meta(&save_dir, &save_id, &meta) {
+        Ok(_) => {
+            info!("Update save meta: {}-{}-{}", game_id, instance_id, save_id);
+            audit_save(
+                &state,
+                &actor,
+                AuditAction::SaveUpdate,
+                &game_id,
+                &instance,
+                &save_id,
+            );
+            state.events.publish(
+                SaveEventKind::Updated,
+                &game_id,
+                instance.get_save_namespace(),
+                &save_id,
+                &meta,
+            );
+            Json(meta).into_response()
+        }
+        Err(err) => {
+            error!(
+                "Failed to write save meta ({game_id}-{instance_id}-{save_id}): {}",
+                err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize, Default, ToSchema)]
+#[serde(default)]
+pub struct SaveShareRequest {
+    /// Lifetime of the link of at most a year, the link never expires when absent.
+    expires_in_hours: Option<u32>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SaveShareResponse {
+    token: String,
+    url: String,
+    expires_at: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/play/{game_id}/{instance_id}/save-sync/access/{save_id}/share",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("save_id", description = "Save id"),
+    ),
+    request_body = Option<SaveShareRequest>,
+    responses(
+        (status = 200, description = "The share link", body = SaveShareResponse),
+        (status = 400, description = "Invalid save id or expiry too far out"),
+        (status = 403, description = "The save is private"),
+        (status = 404, description = "Unknown game, instance or save")
+    )
+)]
+pub async fn handle_save_share(
+    Path((game_id, instance_id, save_id)): Path<(String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+    body: Option<Json<SaveShareRequest>>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+
+    if !is_valid_path_segment(&save_id) {
+        return (StatusCode::BAD_REQUEST, "Invalid save id").into_response();
+    }
+    if !save_exists(&save_dir, &save_id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if let Err(response) =
+        check_save_access(&state, &save_dir, &save_id, &identity, &profile, false)
+    {
+        return response.into_response();
+    }
+
+    let request = body.map(|Json(request)| request).unwrap_or_default();
+    if request
+        .expires_in_hours
+        .is_some_and(|hours| hours > SHARE_MAX_EXPIRES_IN_HOURS)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Share links expire after at most {} hours",
+                SHARE_MAX_EXPIRES_IN_HOURS
+            ),
+        )
+            .into_response();
+    }
+    let expires_in = request
+        .expires_in_hours
+        .map(|hours| Duration::hours(hours as i64));
+
+    match state.shares.create(
+        &game_id,
+        &instance.id,
+        instance.get_save_namespace(),
+        &save_id,
+        expires_in,
+    ) {
+        Ok((token, share)) => {
+            info!("Share save file: {}-{}-{}", game_id, instance_id, save_id);
+            audit_save(
+                &state,
+                &actor,
+                AuditAction::SaveShare,
+                &game_id,
+                &instance,
+                &save_id,
+            );
+            Json(SaveShareResponse {
+                url: format!("/share/save/{}", token),
+                token,
+                expires_at: share.expires_at,
+            })
+            .into_response()
+        }
+        Err(err) => {
+            error!(
+                "Failed to share save file ({game_id}-{instance_id}-{save_id}): {}",
+                err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/play/{game_id}/{instance_id}/save-sync/access",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    request_body = SaveCode,
+    responses(
+        (status = 200, description = "The stored save, `duplicate` when an identical one existed", body = SaveWriteResult),
+        (status = 400, description = "Invalid save alias"),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
 pub async fn handle_save_upload(
     Path((game_id, instance_id)): Path<(String, String)>,
     State(state): State<Arc<AppState>>,
-    Json(save_code): Json<SaveCode>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+    Json(mut save_code): Json<SaveCode>,
 ) -> impl IntoResponse {
-    let game = match extract_game(&state, &game_id) {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
         Ok(result) => result,
         Err(response) => return response.into_response(),
     };
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+
+    if let Some(Extension(profile)) = &profile {
+        save_code.set_alias(profile.name.clone());
+    }
+    let alias = save_code.get_alias_no_empty();
+    if !is_valid_path_segment(&alias) {
+        return (StatusCode::BAD_REQUEST, "Invalid save alias").into_response();
+    }
+    if !is_alias_unlocked(
+        &state,
+        get_identity(&identity),
+        get_profile(&profile),
+        &alias,
+    ) {
+        return (StatusCode::FORBIDDEN, "Pick the household profile first").into_response();
+    }
 
     match write_save_content(
-        &game.get_save_path_append(&instance_id),
+        &save_dir,
         &game_id,
         &instance_id,
         save_code,
+        get_owner(&identity),
     ) {
-        Ok(_) => {
-            info!("Save file successfully: {}-{}", game_id, instance_id);
-            StatusCode::NO_CONTENT.into_response()
+        Ok(result) => {
+            state.events.publish(
+                if result.duplicate {
+                    SaveEventKind::Updated
+                } else {
+                    SaveEventKind::Created
+                },
+                &game_id,
+                instance.get_save_namespace(),
+                &result.id,
+                &read_save_meta(&save_dir, &result.id),
+            );
+            if result.duplicate {
+                info!(
+                    "Save file unchanged: {}-{}-{}",
+                    game_id, instance_id, result.id
+                );
+            } else {
+                info!("Save file successfully: {}-{}", game_id, instance_id);
+                audit_save(
+                    &state,
+                    &actor,
+                    AuditAction::SaveUpload,
+                    &game_id,
+                    &instance,
+                    &result.id,
+                );
+            }
+            Json(result).into_response()
         }
         Err(err) => {
             error!(
@@ -92,118 +612,847 @@ pub async fn handle_save_upload(
     }
 }
 
-#[derive(Deserialize)]
-pub struct SaveCode {
-    pub code: String,
-    alias: String,
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/save-sync/export",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    responses(
+        (status = 200, description = "Zip archive of the saves the caller may read", body = Vec<u8>, content_type = "application/zip"),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
+pub async fn handle_save_export(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+
+    let manifest = SaveArchiveManifest {
+        game_id: game_id.clone(),
+        instance_id: instance.id.clone(),
+        save_namespace: instance.get_save_namespace().to_string(),
+        exported_at: Local::now().to_rfc3339(),
+        saves: Vec::new(),
+    };
+
+    // The archive is built in the staging directory and streamed from there.
+    let staged = match Staged::new(&game) {
+        Ok(staged) => staged,
+        Err(err) => {
+            error!("Failed to stage save export for {}: {}", game_id, err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let identity = get_identity(&identity).cloned();
+    let profile = get_profile(&profile).cloned();
+    let built = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        let file = fs::File::create(staged.get_path())?;
+        let is_unlocked =
+            |alias: &str| is_alias_unlocked(&state, identity.as_ref(), profile.as_ref(), alias);
+        build_save_archive(&save_dir, manifest, identity.as_ref(), is_unlocked, file)?;
+        let file = fs::File::open(staged.get_path())?;
+        let size = file.metadata()?.len();
+        Ok((staged, file, size))
+    })
+    .await;
+
+    match built {
+        Ok(Ok((staged, file, size))) => {
+            info!(
+                "Export save files: {}-{} ({} bytes)",
+                game_id, instance_id, size
+            );
+            let file_name = format!(
+                "{}-{}-{}.zip",
+                game_id,
+                instance.get_save_namespace(),
+                Local::now().format("%Y-%m-%d+%H-%M-%S")
+            );
+            (
+                StatusCode::OK,
+                [
+                    (CONTENT_TYPE, String::from("application/zip")),
+                    (CONTENT_LENGTH, size.to_string()),
+                    (
+                        CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"{}\"", file_name),
+                    ),
+                ],
+                stream_file_body(file, staged),
+            )
+                .into_response()
+        }
+        Ok(Err(err)) => {
+            error!(
+                "Failed to export save files ({game_id}-{instance_id}): {}",
+                err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(err) => {
+            error!(
+                "Failed to export save files ({game_id}-{instance_id}): {}",
+                err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/play/{game_id}/{instance_id}/save-sync/import",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    request_body(content = Vec<u8>, content_type = "application/zip"),
+    responses(
+        (status = 200, description = "Imported, duplicate and rejected saves", body = SaveImportReport),
+        (status = 400, description = "Invalid save archive"),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
+pub async fn handle_save_import(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+
+    let is_unlocked = |alias: &str| {
+        is_alias_unlocked(
+            &state,
+            get_identity(&identity),
+            get_profile(&profile),
+            alias,
+        )
+    };
+    match import_save_archive(&save_dir, &body, get_owner(&identity), is_unlocked) {
+        Ok(report) => {
+            for save_id in &report.imported {
+                state.events.publish(
+                    SaveEventKind::Created,
+                    &game_id,
+                    instance.get_save_namespace(),
+                    save_id,
+                    &read_save_meta(&save_dir, save_id),
+                );
+                audit_save(
+                    &state,
+                    &actor,
+                    AuditAction::SaveImport,
+                    &game_id,
+                    &instance,
+                    save_id,
+                );
+            }
+            info!(
+                "Import save files: {}-{}, {} imported, {} duplicates, {} rejected",
+                game_id,
+                instance_id,
+                report.imported.len(),
+                report.duplicates.len(),
+                report.rejected.len()
+            );
+            Json(report).into_response()
+        }
+        Err(err) => {
+            warn!(
+                "Failed to import save archive ({game_id}-{instance_id}): {}",
+                err
+            );
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid save archive: {}", err),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Streams created, updated and deleted events of the instance's save namespace.
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/save-sync/events",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    responses(
+        (status = 200, description = "Server-sent events named after their kind, plus `lagged` when events were missed", body = SaveEvent, content_type = "text/event-stream"),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
+pub async fn handle_save_events(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    let (_, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+    let save_namespace = instance.get_save_namespace().to_string();
+    let identity = identity.map(|Extension(identity)| identity);
+    let profile = profile.map(|Extension(profile)| profile);
+
+    info!("Subscribe save events: {}-{}", game_id, instance_id);
+
+    let events = state.events.subscribe();
+    let stream = BroadcastStream::new(events).filter_map(move |event| {
+        match event {
+            Ok(event)
+                if event.game_id == game_id
+                    && event.save_namespace == save_namespace
+                    && event.is_visible_to(identity.as_ref())
+                    && is_alias_unlocked(
+                        &state,
+                        identity.as_ref(),
+                        profile.as_ref(),
+                        get_save_alias(&event.save_id),
+                    ) =>
+            {
+                Event::default()
+                    .event(event.kind.as_str())
+                    .json_data(&event)
+                    .ok()
+                    .map(Ok::<_, Infallible>)
+            }
+            Ok(_) => None,
+            // Missed events, tell the client to reload its list.
+            Err(_) => Some(Ok(Event::default().event("lagged").data("{}"))),
+        }
+    });
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
 }
 
-impl SaveCode {
-    fn get_alias_no_empty(&self) -> String {
-        if self.alias.is_empty() {
-            String::from("anonymous")
-        } else {
-            self.alias.clone()
+#[derive(Serialize, ToSchema)]
+struct SaveSyncConfig {
+    auto_sync: bool,
+    auto_sync_interval_secs: u64,
+    /// The picked household profile, whose name replaces the alias of every upload.
+    profile: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/save-sync/config",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    responses(
+        (status = 200, description = "Save sync settings of the game", body = SaveSyncConfig),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
+pub async fn handle_save_sync_config(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    let (game, _) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+
+    Json(SaveSyncConfig {
+        auto_sync: game.game_def.is_use_auto_sync(),
+        auto_sync_interval_secs: game.game_def.auto_sync_interval_secs,
+        profile: profile.map(|Extension(profile)| profile.name),
+    })
+    .into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/play/{game_id}/{instance_id}/save-sync/auto",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    request_body = AutoSaveBatch,
+    responses(
+        (status = 200, description = "Whether the newest save of the batch was stored, and the backups retention removed", body = AutoSaveResult),
+        (status = 400, description = "Invalid save alias or empty batch"),
+        (status = 404, description = "Unknown game or instance, or auto sync is disabled")
+    )
+)]
+pub async fn handle_auto_save_upload(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+    Json(batch): Json<AutoSaveBatch>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+
+    if !game.game_def.is_use_auto_sync() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Auto sync is disabled for game {}", game_id),
+        )
+            .into_response();
+    }
+    let alias = match &profile {
+        Some(Extension(profile)) => profile.name.clone(),
+        None => batch.get_alias_no_empty(),
+    };
+    if !is_valid_path_segment(&alias) {
+        return (StatusCode::BAD_REQUEST, "Invalid save alias").into_response();
+    }
+    if !is_alias_unlocked(
+        &state,
+        get_identity(&identity),
+        get_profile(&profile),
+        &alias,
+    ) {
+        return (StatusCode::FORBIDDEN, "Pick the household profile first").into_response();
+    }
+    // Only the newest state of a batch is worth keeping.
+    let Some(item) = batch.saves.into_iter().last() else {
+        return (StatusCode::BAD_REQUEST, "Empty auto save batch").into_response();
+    };
+
+    match write_auto_save(
+        &save_dir,
+        &alias,
+        &item.code,
+        item.kind.into_iter().collect(),
+        Duration::seconds(game.game_def.auto_sync_interval_secs as i64),
+        game.game_def.auto_sync_retention,
+        get_owner(&identity),
+    ) {
+        Ok(result) => {
+            if result.outcome == AutoSaveOutcome::Created {
+                audit_save(
+                    &state,
+                    &actor,
+                    AuditAction::AutoSave,
+                    &game_id,
+                    &instance,
+                    &result.id,
+                );
+                info!(
+                    "Auto save file: {}-{}-{}, pruned {}",
+                    game_id,
+                    instance_id,
+                    result.id,
+                    result.pruned.len()
+                );
+            }
+            Json(result).into_response()
+        }
+        Err(err) => {
+            error!(
+                "Failed to write auto save file ({game_id}-{instance_id}): {}",
+                err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
 }
 
-fn iter_save_list(save_dir: &PathBuf) -> Vec<String> {
-    if !save_dir.exists() {
-        fs::create_dir(save_dir).unwrap_or_else(|err| {
-            error!("Failed to create save directory: {}", err);
-        });
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/save-sync/auto",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    responses(
+        (status = 200, description = "Automatic save ids, newest first", body = Vec<String>),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
+pub async fn handle_auto_save_list(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+    let auto_dir = get_auto_save_dir(&game.get_save_path_append(instance.get_save_namespace()));
+
+    if !auto_dir.exists() {
+        return Json(Vec::<String>::new()).into_response();
     }
-    fs::read_dir(save_dir)
-        .map(|entries| {
-            entries
-                .filter_map(Result::ok)
-                .filter(|entry| {
-                    entry
-                        .path()
-                        .extension()
-                        .and_then(|ext| ext.to_str())
-                        .map_or(false, |ext_str| ext_str == SAVE_FILE_EXTENSION)
-                })
-                .filter_map(|entry| {
-                    entry
-                        .path()
-                        .file_stem()
-                        .and_then(|stem| stem.to_str())
-                        .map(String::from)
-                })
-                .collect()
-        })
-        .unwrap_or_else(|err| {
-            error!("Failed to read save directory: {}", err);
-            vec![]
+    let mut saves: Vec<String> = iter_save_list(&auto_dir)
+        .into_iter()
+        .filter(|save_id| read_save_meta(&auto_dir, save_id).is_visible_to(get_identity(&identity)))
+        .filter(|save_id| {
+            is_alias_unlocked(
+                &state,
+                get_identity(&identity),
+                get_profile(&profile),
+                get_save_alias(save_id),
+            )
         })
+        .collect();
+    saves.sort_by(|a, b| b.cmp(a));
+    Json(saves).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/save-sync/auto/{save_id}",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("save_id", description = "Save id"),
+    ),
+    responses(
+        (status = 200, description = "The save code", body = String, content_type = "text/plain"),
+        (status = 400, description = "Invalid save id"),
+        (status = 403, description = "The save is private"),
+        (status = 404, description = "Unknown game, instance or save")
+    )
+)]
+pub async fn handle_auto_save_get(
+    Path((game_id, instance_id, save_id)): Path<(String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+    let auto_dir = get_auto_save_dir(&game.get_save_path_append(instance.get_save_namespace()));
+
+    if !is_valid_path_segment(&save_id) {
+        return (StatusCode::BAD_REQUEST, "Invalid save id").into_response();
+    }
+    if let Err(response) =
+        check_save_access(&state, &auto_dir, &save_id, &identity, &profile, false)
+    {
+        return response.into_response();
+    }
+
+    match get_save_content(&auto_dir, &save_id) {
+        Some(content) => content.into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
 }
 
-fn get_save_content(save_dir: &PathBuf, save_id: &str) -> Option<String> {
-    if !save_dir.exists() {
-        fs::create_dir(save_dir).unwrap_or_else(|err| {
-            error!("Failed to create save directory: {}", err);
-        });
+/// Copies an automatic save into the manual saves, where retention never removes it.
+#[utoipa::path(
+    post,
+    path = "/play/{game_id}/{instance_id}/save-sync/auto/{save_id}/keep",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("save_id", description = "Save id"),
+    ),
+    responses(
+        (status = 200, description = "The manual save it was copied to", body = SaveWriteResult),
+        (status = 400, description = "Invalid save id"),
+        (status = 403, description = "The save is private"),
+        (status = 404, description = "Unknown game, instance or save")
+    )
+)]
+pub async fn handle_auto_save_keep(
+    Path((game_id, instance_id, save_id)): Path<(String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+    let auto_dir = get_auto_save_dir(&save_dir);
+
+    if !is_valid_path_segment(&save_id) {
+        return (StatusCode::BAD_REQUEST, "Invalid save id").into_response();
+    }
+    if !save_exists(&auto_dir, &save_id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if let Err(response) =
+        check_save_access(&state, &auto_dir, &save_id, &identity, &profile, false)
+    {
+        return response.into_response();
     }
-    match fs::read_to_string(save_dir.join(assemble_save_name(&save_id))) {
-        Ok(file) => Some(file),
+
+    match copy_save(&auto_dir, &save_id, &save_dir, get_owner(&identity)) {
+        Ok(result) => {
+            if !result.duplicate {
+                state.events.publish(
+                    SaveEventKind::Created,
+                    &game_id,
+                    instance.get_save_namespace(),
+                    &result.id,
+                    &read_save_meta(&save_dir, &result.id),
+                );
+                audit_save(
+                    &state,
+                    &actor,
+                    AuditAction::SaveCopy,
+                    &game_id,
+                    &instance,
+                    &result.id,
+                );
+            }
+            info!(
+                "Keep auto save file: {}-{}-{} as {}",
+                game_id, instance_id, save_id, result.id
+            );
+            Json(result).into_response()
+        }
         Err(err) => {
-            error!("Failed to read save file ({}) : {}", save_id, err);
-            None
+            error!(
+                "Failed to keep auto save file ({game_id}-{instance_id}-{save_id}): {}",
+                err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
     }
 }
 
-fn del_save_content(save_dir: &PathBuf, save_id: &str) {
-    if !save_dir.exists() {
-        fs::create_dir(save_dir).unwrap_or_else(|err| {
-            error!("Failed to create save directory: {}", err);
-        });
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/save-sync/profile/{alias}",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("alias", description = "Save alias"),
+    ),
+    responses(
+        (status = 200, description = "Keys stored for the alias", body = Vec<String>),
+        (status = 400, description = "Invalid alias"),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
+pub async fn handle_profile_list(
+    Path((game_id, instance_id, alias)): Path<(String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+
+    let alias = match get_profile_alias(&state, &identity, &profile, alias) {
+        Ok(alias) => alias,
+        Err(response) => return response.into_response(),
+    };
+
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+    let owner_dir = get_owner_dir(&save_dir, get_owner(&identity));
+    Json(list_profile_keys(&get_profile_dir(&owner_dir, &alias))).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/save-sync/profile/{alias}/{key}",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("alias", description = "Save alias"),
+        ("key", description = "Value key"),
+    ),
+    responses(
+        (status = 200, description = "The stored value", body = String, content_type = "text/plain"),
+        (status = 400, description = "Invalid alias or key"),
+        (status = 404, description = "Unknown game, instance or key")
+    )
+)]
+pub async fn handle_profile_get(
+    Path((game_id, instance_id, alias, key)): Path<(String, String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+
+    let alias = match get_profile_alias(&state, &identity, &profile, alias) {
+        Ok(alias) => alias,
+        Err(response) => return response.into_response(),
+    };
+    if !is_valid_path_segment(&key) {
+        return (StatusCode::BAD_REQUEST, "Invalid key").into_response();
+    }
+
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+    let owner_dir = get_owner_dir(&save_dir, get_owner(&identity));
+    match get_profile_value(&get_profile_dir(&owner_dir, &alias), &key) {
+        Some(value) => value.into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/play/{game_id}/{instance_id}/save-sync/profile/{alias}/{key}",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("alias", description = "Save alias"),
+        ("key", description = "Value key"),
+    ),
+    request_body(content = String, content_type = "text/plain"),
+    responses(
+        (status = 204, description = "The value was stored"),
+        (status = 400, description = "Invalid alias or key"),
+        (status = 404, description = "Unknown game or instance")
+    )
+)]
+pub async fn handle_profile_put(
+    Path((game_id, instance_id, alias, key)): Path<(String, String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+    value: String,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+
+    let alias = match get_profile_alias(&state, &identity, &profile, alias) {
+        Ok(alias) => alias,
+        Err(response) => return response.into_response(),
+    };
+    if !is_valid_path_segment(&key) {
+        return (StatusCode::BAD_REQUEST, "Invalid key").into_response();
     }
-    let file_path = save_dir.join(assemble_save_name(&save_id));
-    if file_path.exists() {
-        if let Err(err) = fs::remove_file(&file_path) {
-            error!("Failed to delete save file ({}) : {}", save_id, err);
+
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+    let owner_dir = get_owner_dir(&save_dir, get_owner(&identity));
+    match write_profile_value(&get_profile_dir(&owner_dir, &alias), &key, &value) {
+        Ok(_) => {
+            info!(
+                "Write profile value: {}-{}-{}-{}",
+                game_id, instance_id, alias, key
+            );
+            audit_save(
+                &state,
+                &actor,
+                AuditAction::ProfileWrite,
+                &game_id,
+                &instance,
+                &format!("{}/{}", alias, key),
+            );
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(err) => {
+            error!(
+                "Failed to write profile value ({game_id}-{instance_id}-{alias}-{key}): {}",
+                err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
         }
-    } else {
-        warn!("Save file not found for deletion: {}", file_path.display());
     }
 }
 
-fn write_save_content(
-    save_dir: &PathBuf,
-    game_id: &str,
-    instance_id: &str,
-    code: SaveCode,
-) -> Result<()> {
-    let now = Local::now();
-    let timestamp = now.format("%Y-%m-%d+%H-%M-%S").to_string();
-    let file_name = format!(
-        "{}@{}.{}",
-        code.get_alias_no_empty(),
-        timestamp,
-        SAVE_FILE_EXTENSION
-    );
+#[utoipa::path(
+    delete,
+    path = "/play/{game_id}/{instance_id}/save-sync/profile/{alias}/{key}",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+        ("alias", description = "Save alias"),
+        ("key", description = "Value key"),
+    ),
+    responses(
+        (status = 204, description = "The value was deleted"),
+        (status = 400, description = "Invalid alias or key"),
+        (status = 404, description = "Unknown game, instance or key")
+    )
+)]
+pub async fn handle_profile_del(
+    Path((game_id, instance_id, alias, key)): Path<(String, String, String, String)>,
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+    profile: Option<Extension<HouseholdProfile>>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
 
-    if !save_dir.exists() {
-        fs::create_dir(save_dir).unwrap_or_else(|err| {
-            error!("Failed to create save directory: {}", err);
-        });
+    let alias = match get_profile_alias(&state, &identity, &profile, alias) {
+        Ok(alias) => alias,
+        Err(response) => return response.into_response(),
+    };
+    if !is_valid_path_segment(&key) {
+        return (StatusCode::BAD_REQUEST, "Invalid key").into_response();
     }
-    let file_path = save_dir.join(file_name);
-    if file_path.exists() {
-        warn!(
-            "Save file already exists ({game_id}-{instance_id}): {}",
-            file_path.display()
+
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+    let owner_dir = get_owner_dir(&save_dir, get_owner(&identity));
+    if del_profile_value(&get_profile_dir(&owner_dir, &alias), &key) {
+        info!(
+            "Delete profile value: {}-{}-{}-{}",
+            game_id, instance_id, alias, key
         );
+        audit_save(
+            &state,
+            &actor,
+            AuditAction::ProfileDelete,
+            &game_id,
+            &instance,
+            &format!("{}/{}", alias, key),
+        );
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
     }
-    let mut file = fs::File::create(&file_path)?;
-    file.write_all(code.code.as_bytes())?;
+}
 
-    Ok(())
+#[utoipa::path(
+    get,
+    path = "/play/{game_id}/{instance_id}/storage-backup",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    responses(
+        (status = 200, description = "The last storage snapshot", body = Object),
+        (status = 404, description = "Unknown game or instance, no snapshot, or storage backup is disabled")
+    )
+)]
+pub async fn handle_storage_backup_get(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+
+    if !game.game_def.is_use_storage_backup() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Storage backup is disabled for game {}", game_id),
+        )
+            .into_response();
+    }
+
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+    let owner_dir = get_owner_dir(&save_dir, get_owner(&identity));
+    match get_storage_backup(&owner_dir) {
+        Some(snapshot) => ([(CONTENT_TYPE, "application/json")], snapshot).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
 }
 
-fn assemble_save_name(save_id: &str) -> String {
-    format!("{}.{}", save_id, SAVE_FILE_EXTENSION)
+#[utoipa::path(
+    put,
+    path = "/play/{game_id}/{instance_id}/storage-backup",
+    tag = "save-sync",
+    params(
+        ("game_id", description = "Game id"),
+        ("instance_id", description = "Instance id or alias"),
+    ),
+    request_body(content = Object, content_type = "application/json"),
+    responses(
+        (status = 204, description = "The snapshot was stored"),
+        (status = 400, description = "The snapshot is not valid JSON"),
+        (status = 404, description = "Unknown game or instance, or storage backup is disabled")
+    )
+)]
+pub async fn handle_storage_backup_put(
+    Path((game_id, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+    actor: Actor,
+    snapshot: String,
+) -> impl IntoResponse {
+    let (game, instance) = match extract_game_instance(&state, &game_id, &instance_id) {
+        Ok(result) => result,
+        Err(response) => return response.into_response(),
+    };
+
+    if !game.game_def.is_use_storage_backup() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Storage backup is disabled for game {}", game_id),
+        )
+            .into_response();
+    }
+    if serde_json::from_str::<serde_json::Value>(&snapshot).is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Storage snapshot is not valid JSON",
+        )
+            .into_response();
+    }
+
+    let save_dir = game.get_save_path_append(instance.get_save_namespace());
+    let owner_dir = get_owner_dir(&save_dir, get_owner(&identity));
+    match write_storage_backup(&owner_dir, &snapshot) {
+        Ok(_) => {
+            info!("Write storage backup: {}-{}", game_id, instance_id);
+            audit_save(
+                &state,
+                &actor,
+                AuditAction::StorageBackup,
+                &game_id,
+                &instance,
+                "storage-backup",
+            );
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(err) => {
+            error!(
+                "Failed to write storage backup ({game_id}-{instance_id}): {}",
+                err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
 }
@@ -0,0 +1,220 @@
+use crate::foundation::audit::{Actor, AuditAction, AuditTarget};
+use crate::foundation::event::SaveEventKind;
+use crate::foundation::registry::Registry;
+use crate::foundation::save::{copy_save, get_save_content, read_save_meta};
+use crate::foundation::share::SaveShare;
+use crate::foundation::user::Identity;
+use crate::util::AppState;
+use askama::Template;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::response::{Html, IntoResponse, Redirect};
+use axum::routing::{get, post};
+use axum::{Extension, Router};
+use std::sync::Arc;
+use tracing::{error, info};
+use utoipa::OpenApi;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/save/{token}", get(handle_share_page))
+        .route("/save/{token}/raw", get(handle_share_raw))
+        .route("/save/{token}/load/{instance_id}", post(handle_share_load))
+}
+
+/// The download only, the share page and loading a save are for browsers.
+#[derive(OpenApi)]
+#[openapi(paths(handle_share_raw))]
+pub(super) struct ShareApi;
+
+#[derive(Template)]
+#[template(path = "share.html")]
+struct ShareTemplate {
+    token: String,
+    share: SaveShare,
+    game_name: String,
+    instance_name: String,
+    note: Option<String>,
+    tags: Vec<String>,
+    size: usize,
+    targets: Vec<(String, String)>,
+}
+
+fn extract_share(
+    state: &Arc<AppState>,
+    token: &str,
+) -> Result<SaveShare, (StatusCode, &'static str)> {
+    match state.shares.get(token) {
+        Some(share) if share.is_expired() => Err((StatusCode::GONE, "This share link has expired")),
+        Some(share) => Ok(share),
+        None => Err((StatusCode::NOT_FOUND, "Unknown share link")),
+    }
+}
+
+async fn handle_share_page(
+    Path(token): Path<String>,
+    State(state): State<Arc<AppState>>,
+    identity: Option<Extension<Identity>>,
+) -> impl IntoResponse {
+    let identity = identity.map(|Extension(identity)| identity);
+    let share = match extract_share(&state, &token) {
+        Ok(share) => share,
+        Err(response) => return response.into_response(),
+    };
+    let Some(game) = state.registry.get(&share.game_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            "The shared save's game no longer exists",
+        )
+            .into_response();
+    };
+
+    let save_dir = game.get_save_path_append(&share.save_namespace);
+    let Some(content) = get_save_content(&save_dir, &share.save_id) else {
+        return (StatusCode::NOT_FOUND, "The shared save no longer exists").into_response();
+    };
+    let meta = read_save_meta(&save_dir, &share.save_id);
+
+    let mut targets: Vec<(String, String)> = game
+        .instances
+        .values()
+        .filter(|instance| {
+            game.allows(identity.as_ref()) && instance.access.allows(identity.as_ref())
+        })
+        .map(|instance| (instance.id.clone(), instance.get_name()))
+        .collect();
+    targets.sort();
+
+    let template = ShareTemplate {
+        game_name: game.game_def.name.clone().unwrap_or(game.id.clone()),
+        instance_name: game
+            .get_instance(&share.instance_id)
+            .map(|instance| instance.get_name())
+            .unwrap_or(share.instance_id.clone()),
+        note: meta.note,
+        tags: meta.tags,
+        size: content.len(),
+        targets,
+        token,
+        share,
+    };
+
+    match template.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(err) => {
+            error!("Failed to render share page: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/share/save/{token}/raw",
+    tag = "save-sync",
+    params(("token", description = "Share token")),
+    responses(
+        (status = 200, description = "The shared save code", body = String, content_type = "text/plain"),
+        (status = 404, description = "Unknown share, or the save is gone"),
+        (status = 410, description = "The share has expired")
+    )
+)]
+async fn handle_share_raw(
+    Path(token): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let share = match extract_share(&state, &token) {
+        Ok(share) => share,
+        Err(response) => return response.into_response(),
+    };
+    let Some(game) = state.registry.get(&share.game_id) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match get_save_content(
+        &game.get_save_path_append(&share.save_namespace),
+        &share.save_id,
+    ) {
+        Some(content) => (
+            StatusCode::OK,
+            [
+                (CONTENT_TYPE, String::from("text/plain; charset=utf-8")),
+                (
+                    CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.save\"", share.save_id),
+                ),
+            ],
+            content,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn handle_share_load(
+    Path((token, instance_id)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    actor: Actor,
+    identity: Option<Extension<Identity>>,
+) -> impl IntoResponse {
+    let share = match extract_share(&state, &token) {
+        Ok(share) => share,
+        Err(response) => return response.into_response(),
+    };
+    let Some(game) = state.registry.get(&share.game_id) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Some(target) = game.get_instance(&instance_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("no instance found with id {}", instance_id),
+        )
+            .into_response();
+    };
+    let identity = identity.map(|Extension(identity)| identity);
+    if !game.allows(identity.as_ref()) || !target.access.allows(identity.as_ref()) {
+        return (StatusCode::FORBIDDEN, "Access denied").into_response();
+    }
+
+    let target_dir = game.get_save_path_append(target.get_save_namespace());
+    match copy_save(
+        &game.get_save_path_append(&share.save_namespace),
+        &share.save_id,
+        &target_dir,
+        identity.as_ref().map(|identity| identity.name.as_str()),
+    ) {
+        Ok(result) => {
+            if !result.duplicate {
+                state.events.publish(
+                    SaveEventKind::Created,
+                    &share.game_id,
+                    target.get_save_namespace(),
+                    &result.id,
+                    &read_save_meta(&target_dir, &result.id),
+                );
+                state.audit.record(
+                    &actor,
+                    AuditAction::SaveCopy,
+                    AuditTarget {
+                        game: Some(&share.game_id),
+                        instance: Some(&target.id),
+                        target: Some(&result.id),
+                    },
+                );
+            }
+            info!(
+                "Load shared save {}-{} into {} as {}",
+                share.game_id, share.save_id, target.id, result.id
+            );
+            Redirect::to(&format!("/play/{}/{}/index", share.game_id, target.id)).into_response()
+        }
+        Err(err) => {
+            error!(
+                "Failed to load shared save {}-{} into {}: {}",
+                share.game_id, share.save_id, target.id, err
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use super::admin::get_managed_game;
+use crate::foundation::stats::{GameStats, get_game_stats};
+use crate::foundation::user::Identity;
+use crate::util::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Extension, Json, Router, middleware};
+use std::sync::Arc;
+use tracing::error;
+use utoipa::OpenApi;
+
+/// Admin endpoint reporting the disk usage of a game.
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/games/{game_id}/stats", get(handle_game_stats))
+        .route_layer(middleware::from_fn(super::auth::require_admin))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(handle_game_stats))]
+pub(super) struct StatsApi;
+
+#[utoipa::path(
+    get,
+    path = "/api/games/{game_id}/stats",
+    tag = "content",
+    params(("game_id", description = "Game id")),
+    responses(
+        (status = 200, description = "Disk usage of the game", body = GameStats),
+        (status = 404, description = "Unknown game")
+    )
+)]
+async fn handle_game_stats(
+    Path(game_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+) -> impl IntoResponse {
+    let game = match get_managed_game(&state, &identity, &game_id) {
+        Ok(game) => game,
+        Err(err) => return err.into_response(),
+    };
+
+    match tokio::task::spawn_blocking(move || get_game_stats(&game)).await {
+        Ok(Ok(stats)) => Json(stats).into_response(),
+        Ok(Err(err)) => {
+            error!("Failed to measure game {}: {}", game_id, err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,544 @@
+use super::admin::get_managed_game;
+use crate::foundation::audit::{Actor, AuditAction, AuditTarget};
+use crate::foundation::registry::reload_game;
+use crate::foundation::release::{ReleaseImport, add_release, extract_release};
+use crate::foundation::structure::GameInfo;
+use crate::foundation::upload::{
+    INDEX_UPLOAD_MAX_SIZE, LAYER_UPLOAD_MAX_SIZE, MOD_UPLOAD_MAX_SIZE, Staged, add_index,
+    add_layer, add_mod, extract_layer, is_valid_content_id,
+};
+use crate::foundation::user::Identity;
+use crate::util::AppState;
+use anyhow::Result;
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::header::CONTENT_LENGTH;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{post, put};
+use axum::{Extension, Json, Router, middleware};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio_stream::StreamExt;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+/// Admin endpoints adding game content, each takes the raw file as the request body.
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/api/games/{game_id}/indexes/{index_id}",
+            put(handle_index_upload),
+        )
+        .route("/api/games/{game_id}/mods/{mod_id}", put(handle_mod_upload))
+        .route(
+            "/api/games/{game_id}/layers/{layer_id}",
+            put(handle_layer_upload),
+        )
+        .route("/api/games/{game_id}/releases", post(handle_release_import))
+        .route("/api/games/{game_id}/reload", post(handle_game_reload))
+        .route_layer(middleware::from_fn(super::auth::require_admin))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    handle_index_upload,
+    handle_mod_upload,
+    handle_layer_upload,
+    handle_release_import,
+    handle_game_reload
+))]
+pub(super) struct UploadApi;
+
+#[derive(Clone, Copy)]
+enum UploadKind {
+    Index,
+    Mod,
+    Layer,
+}
+
+impl UploadKind {
+    fn get_name(self) -> &'static str {
+        match self {
+            UploadKind::Index => "index",
+            UploadKind::Mod => "mod",
+            UploadKind::Layer => "layer",
+        }
+    }
+
+    fn get_max_size(self) -> u64 {
+        match self {
+            UploadKind::Index => INDEX_UPLOAD_MAX_SIZE,
+            UploadKind::Mod => MOD_UPLOAD_MAX_SIZE,
+            UploadKind::Layer => LAYER_UPLOAD_MAX_SIZE,
+        }
+    }
+
+    fn get_action(self) -> AuditAction {
+        match self {
+            UploadKind::Index => AuditAction::IndexUpload,
+            UploadKind::Mod => AuditAction::ModUpload,
+            UploadKind::Layer => AuditAction::LayerUpload,
+        }
+    }
+
+    fn exists(self, game: &GameInfo, id: &str) -> bool {
+        match self {
+            UploadKind::Index => game.indexes.contains_key(id),
+            UploadKind::Mod => game.mods.contains_key(id),
+            UploadKind::Layer => game.layers.contains_key(id),
+        }
+    }
+}
+
+/// Unpacks a staged upload ahead of [`add_content`], returns the number of files of a layer.
+fn prepare_content(
+    kind: UploadKind,
+    game: &GameInfo,
+    staged: Staged,
+) -> Result<(Staged, Option<usize>)> {
+    match kind {
+        UploadKind::Index | UploadKind::Mod => Ok((staged, None)),
+        UploadKind::Layer => {
+            extract_layer(game, staged).map(|(extracted, files)| (extracted, Some(files)))
+        }
+    }
+}
+
+/// Places and registers a prepared upload.
+fn add_content(kind: UploadKind, game: &mut GameInfo, id: &str, staged: Staged) -> Result<()> {
+    match kind {
+        UploadKind::Index => add_index(game, id, staged),
+        UploadKind::Mod => add_mod(game, id, staged),
+        UploadKind::Layer => add_layer(game, id, staged),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct UploadResponse {
+    id: String,
+    /// Size of the upload in bytes.
+    size: u64,
+    /// Files extracted from a layer archive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<usize>,
+}
+
+fn too_large(max_size: u64) -> (StatusCode, String) {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("Upload exceeds {} MiB", max_size / 1024 / 1024),
+    )
+}
+
+/// Rejects uploads announcing more than `max_size` bytes before receiving them.
+fn check_content_length(headers: &HeaderMap, max_size: u64) -> Result<(), (StatusCode, String)> {
+    if headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .is_some_and(|length| length > max_size)
+    {
+        return Err(too_large(max_size));
+    }
+    Ok(())
+}
+
+/// Streams a request body into `staged`, failing once it exceeds `max_size` bytes.
+async fn receive_upload(
+    body: Body,
+    staged: &Staged,
+    max_size: u64,
+) -> Result<u64, (StatusCode, String)> {
+    let write_failed = |err: std::io::Error| {
+        error!("Failed to write upload {:?}: {}", staged.get_path(), err);
+        (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+    };
+
+    let mut file = File::create(staged.get_path())
+        .await
+        .map_err(write_failed)?;
+    let mut stream = body.into_data_stream();
+    let mut size: u64 = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+        size += chunk.len() as u64;
+        if size > max_size {
+            return Err(too_large(max_size));
+        }
+        file.write_all(&chunk).await.map_err(write_failed)?;
+    }
+    // Writes may still be in flight on the blocking pool until flushed.
+    file.flush().await.map_err(write_failed)?;
+    Ok(size)
+}
+
+async fn handle_upload(
+    state: Arc<AppState>,
+    identity: Identity,
+    actor: Actor,
+    (game_id, id): (String, String),
+    kind: UploadKind,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let game = match get_managed_game(&state, &identity, &game_id) {
+        Ok(game) => game,
+        Err(err) => return err.into_response(),
+    };
+    if !is_valid_content_id(&id) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid {} id '{}'", kind.get_name(), id),
+        )
+            .into_response();
+    }
+    if matches!(kind, UploadKind::Mod) && !game.game_def.use_mods {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Mods are disabled for game {}", game_id),
+        )
+            .into_response();
+    }
+    if kind.exists(&game, &id) {
+        return (
+            StatusCode::CONFLICT,
+            format!("{} '{}' already exists", kind.get_name(), id),
+        )
+            .into_response();
+    }
+    let max_size = kind.get_max_size();
+    if let Err(err) = check_content_length(&headers, max_size) {
+        return err.into_response();
+    }
+
+    let staged = match Staged::new(&game) {
+        Ok(staged) => staged,
+        Err(err) => {
+            error!("Failed to stage upload for {}: {}", game_id, err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+        }
+    };
+    let size = match receive_upload(body, &staged, max_size).await {
+        Ok(size) => size,
+        Err(err) => return err.into_response(),
+    };
+
+    // Extracting a layer takes a while, keep it off the async workers and outside the registry
+    // update, which only places the result.
+    let files = {
+        let state = state.clone();
+        let (game_id, id) = (game_id.clone(), id.clone());
+        tokio::task::spawn_blocking(move || match prepare_content(kind, &game, staged) {
+            Ok((staged, files)) => state.registry.update(&game_id, |game| {
+                add_content(kind, game, &id, staged).map(|_| files)
+            }),
+            Err(err) => Some(Err(err)),
+        })
+        .await
+    };
+    let files = match files {
+        Ok(Some(Ok(files))) => files,
+        Ok(Some(Err(err))) => {
+            warn!(
+                "Rejected {} upload {}/{}: {}",
+                kind.get_name(),
+                game_id,
+                id,
+                err
+            );
+            return (StatusCode::BAD_REQUEST, err.to_string()).into_response();
+        }
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("no game found with id {}", game_id),
+            )
+                .into_response();
+        }
+        Err(err) => {
+            error!("Failed to place upload {}/{}: {}", game_id, id, err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+        }
+    };
+
+    info!(
+        "Uploaded {} {}/{} ({} bytes) by {}",
+        kind.get_name(),
+        game_id,
+        id,
+        size,
+        identity.name
+    );
+    state.audit.record(
+        &actor,
+        kind.get_action(),
+        AuditTarget {
+            game: Some(&game_id),
+            target: Some(&id),
+            ..Default::default()
+        },
+    );
+    (
+        StatusCode::CREATED,
+        Json(UploadResponse { id, size, files }),
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/games/{game_id}/indexes/{index_id}",
+    tag = "content",
+    params(
+        ("game_id", description = "Game id"),
+        ("index_id", description = "Id of the new index"),
+    ),
+    request_body(content = Vec<u8>, description = "The html file", content_type = "text/html"),
+    responses(
+        (status = 201, description = "The index was added", body = UploadResponse),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "Unknown game"),
+        (status = 409, description = "The index already exists"),
+        (status = 413, description = "The upload is too large")
+    )
+)]
+async fn handle_index_upload(
+    Path(path): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    headers: HeaderMap,
+    body: Body,
+) -> impl IntoResponse {
+    handle_upload(
+        state,
+        identity,
+        actor,
+        path,
+        UploadKind::Index,
+        headers,
+        body,
+    )
+    .await
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/games/{game_id}/mods/{mod_id}",
+    tag = "content",
+    params(
+        ("game_id", description = "Game id"),
+        ("mod_id", description = "Id of the new mod"),
+    ),
+    request_body(content = Vec<u8>, description = "The mod zip", content_type = "application/zip"),
+    responses(
+        (status = 201, description = "The mod was added", body = UploadResponse),
+        (status = 400, description = "Invalid id, or the game does not use mods"),
+        (status = 404, description = "Unknown game"),
+        (status = 409, description = "The mod already exists"),
+        (status = 413, description = "The upload is too large")
+    )
+)]
+async fn handle_mod_upload(
+    Path(path): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    headers: HeaderMap,
+    body: Body,
+) -> impl IntoResponse {
+    handle_upload(state, identity, actor, path, UploadKind::Mod, headers, body).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/games/{game_id}/layers/{layer_id}",
+    tag = "content",
+    params(
+        ("game_id", description = "Game id"),
+        ("layer_id", description = "Id of the new layer"),
+    ),
+    request_body(content = Vec<u8>, description = "A zip, tar or tar.gz archive", content_type = "application/octet-stream"),
+    responses(
+        (status = 201, description = "The layer was added", body = UploadResponse),
+        (status = 400, description = "Invalid id, or invalid archive"),
+        (status = 404, description = "Unknown game"),
+        (status = 409, description = "The layer already exists"),
+        (status = 413, description = "The upload is too large")
+    )
+)]
+async fn handle_layer_upload(
+    Path(path): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    headers: HeaderMap,
+    body: Body,
+) -> impl IntoResponse {
+    handle_upload(
+        state,
+        identity,
+        actor,
+        path,
+        UploadKind::Layer,
+        headers,
+        body,
+    )
+    .await
+}
+
+#[derive(Deserialize, Default, IntoParams)]
+#[serde(default)]
+#[into_params(parameter_in = Query)]
+struct ReleaseImportQuery {
+    /// Id of the index, inferred from the html file or `filename` when omitted.
+    version: Option<String>,
+    /// Also create an instance with this id.
+    instance: Option<String>,
+    /// Name of the uploaded archive.
+    filename: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_id}/releases",
+    tag = "content",
+    params(("game_id", description = "Game id"), ReleaseImportQuery),
+    request_body(content = Vec<u8>, description = "An official release archive", content_type = "application/octet-stream"),
+    responses(
+        (status = 201, description = "The index and layer the release was imported as", body = ReleaseImport),
+        (status = 400, description = "Invalid release archive"),
+        (status = 404, description = "Unknown game"),
+        (status = 413, description = "The upload is too large")
+    )
+)]
+async fn handle_release_import(
+    Path(game_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    Query(query): Query<ReleaseImportQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> impl IntoResponse {
+    let game = match get_managed_game(&state, &identity, &game_id) {
+        Ok(game) => game,
+        Err(err) => return err.into_response(),
+    };
+    if let Err(err) = check_content_length(&headers, LAYER_UPLOAD_MAX_SIZE) {
+        return err.into_response();
+    }
+
+    let staged = match Staged::new(&game) {
+        Ok(staged) => staged,
+        Err(err) => {
+            error!("Failed to stage upload for {}: {}", game_id, err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+        }
+    };
+    if let Err(err) = receive_upload(body, &staged, LAYER_UPLOAD_MAX_SIZE).await {
+        return err.into_response();
+    }
+
+    // Only placing the extracted release happens while the game is locked.
+    let imported = {
+        let state = state.clone();
+        let game_id = game_id.clone();
+        tokio::task::spawn_blocking(move || {
+            let release = extract_release(
+                &game,
+                staged.get_path(),
+                query.filename.as_deref(),
+                query.version.as_deref(),
+            );
+            match release {
+                Ok(release) => state.registry.update(&game_id, |game| {
+                    add_release(game, release, query.instance.as_deref())
+                }),
+                Err(err) => Some(Err(err)),
+            }
+        })
+        .await
+    };
+    let imported = match imported {
+        Ok(Some(Ok(imported))) => imported,
+        Ok(Some(Err(err))) => {
+            warn!("Rejected release for {}: {}", game_id, err);
+            return (StatusCode::BAD_REQUEST, err.to_string()).into_response();
+        }
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("no game found with id {}", game_id),
+            )
+                .into_response();
+        }
+        Err(err) => {
+            error!("Failed to import release for {}: {}", game_id, err);
+            return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+        }
+    };
+
+    info!(
+        "Imported release {}/{} by {}",
+        game_id, imported.version, identity.name
+    );
+    state.audit.record(
+        &actor,
+        AuditAction::ReleaseImport,
+        AuditTarget {
+            game: Some(&game_id),
+            instance: imported.instance.as_deref(),
+            target: Some(&imported.version),
+        },
+    );
+    (StatusCode::CREATED, Json(imported)).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/games/{game_id}/reload",
+    tag = "content",
+    params(("game_id", description = "Game id")),
+    responses(
+        (status = 204, description = "The game was rescanned from disk"),
+        (status = 404, description = "Unknown game")
+    )
+)]
+async fn handle_game_reload(
+    Path(game_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+) -> impl IntoResponse {
+    if let Err(err) = get_managed_game(&state, &identity, &game_id) {
+        return err.into_response();
+    }
+
+    match reload_game(&state.registry, &game_id) {
+        Some(Ok(())) => {
+            info!("Game {} reloaded by {}", game_id, identity.name);
+            state.audit.record(
+                &actor,
+                AuditAction::GameReload,
+                AuditTarget {
+                    game: Some(&game_id),
+                    ..Default::default()
+                },
+            );
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Some(Err(err)) => {
+            error!("Failed to reload game {}: {}", game_id, err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("no game found with id {}", game_id),
+        )
+            .into_response(),
+    }
+}
@@ -0,0 +1,222 @@
+use anyhow::{Result, anyhow};
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use zip::ZipArchive;
+
+/// Archive formats layers can be uploaded in, detected by their content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Reads the magic bytes at the start of `file` and rewinds it.
+    pub fn detect(file: &mut File) -> Result<Self> {
+        let mut header = Vec::with_capacity(262);
+        file.by_ref().take(262).read_to_end(&mut header)?;
+        file.seek(SeekFrom::Start(0))?;
+
+        if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Ok(ArchiveFormat::Zip)
+        } else if header.starts_with(&[0x1f, 0x8b]) {
+            Ok(ArchiveFormat::TarGz)
+        } else if header.get(257..262) == Some(b"ustar") {
+            Ok(ArchiveFormat::Tar)
+        } else {
+            Err(anyhow!("Unsupported archive, expected zip, tar or tar.gz"))
+        }
+    }
+}
+
+/// Extracts a zip, tar or tar.gz archive into `target` and returns the number of extracted
+/// files. Entries escaping `target` fail the extraction, links are skipped and at most
+/// `max_size` bytes are unpacked.
+pub fn extract_archive(archive_path: &Path, target: &Path, max_size: u64) -> Result<usize> {
+    let mut file = File::open(archive_path)?;
+    let format = ArchiveFormat::detect(&mut file)?;
+    fs::create_dir_all(target)?;
+
+    let mut budget = max_size;
+    match format {
+        ArchiveFormat::Zip => extract_zip(file, target, &mut budget),
+        ArchiveFormat::Tar => extract_tar(file, target, &mut budget),
+        ArchiveFormat::TarGz => extract_tar(GzDecoder::new(file), target, &mut budget),
+    }
+}
+
+/// The format of an archive and the paths of the files in it.
+pub fn list_archive_files(archive_path: &Path) -> Result<(ArchiveFormat, Vec<String>)> {
+    let mut file = File::open(archive_path)?;
+    let format = ArchiveFormat::detect(&mut file)?;
+
+    let files = match format {
+        ArchiveFormat::Zip => {
+            let archive = ZipArchive::new(file)?;
+            archive
+                .file_names()
+                .filter(|name| !name.ends_with('/'))
+                .map(str::to_string)
+                .collect()
+        }
+        ArchiveFormat::Tar => list_tar_files(file)?,
+        ArchiveFormat::TarGz => list_tar_files(GzDecoder::new(file))?,
+    };
+    Ok((format, files))
+}
+
+fn list_tar_files(reader: impl Read) -> Result<Vec<String>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.header().entry_type().is_file() {
+            files.push(entry.path()?.to_string_lossy().to_string());
+        }
+    }
+    Ok(files)
+}
+
+fn extract_zip(file: File, target: &Path, budget: &mut u64) -> Result<usize> {
+    let mut archive = ZipArchive::new(file)?;
+    let mut files = 0;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(relative) = entry.enclosed_name() else {
+            return Err(anyhow!(
+                "Archive entry '{}' escapes the layer",
+                entry.name()
+            ));
+        };
+        let path = target.join(relative);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else if entry.is_file() {
+            write_entry(&mut entry, &path, budget)?;
+            files += 1;
+        }
+    }
+
+    Ok(files)
+}
+
+fn extract_tar(reader: impl Read, target: &Path, budget: &mut u64) -> Result<usize> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = 0;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let Some(relative) = enclosed_path(&entry_path) else {
+            return Err(anyhow!(
+                "Archive entry '{}' escapes the layer",
+                entry_path.display()
+            ));
+        };
+        let path = target.join(relative);
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else if entry_type.is_file() {
+            write_entry(&mut entry, &path, budget)?;
+            files += 1;
+        }
+    }
+
+    Ok(files)
+}
+
+/// The path relative to the extraction target, `None` for absolute paths and `..`.
+fn enclosed_path(path: &Path) -> Option<PathBuf> {
+    let mut enclosed = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => enclosed.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(enclosed)
+}
+
+fn write_entry(reader: &mut impl Read, path: &Path, budget: &mut u64) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(path)?;
+    // One byte over the budget tells a file filling it exactly from one exceeding it.
+    let written = io::copy(&mut reader.take(*budget + 1), &mut file)?;
+    if written > *budget {
+        return Err(anyhow!("Archive exceeds the extracted size limit"));
+    }
+    *budget -= written;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::file::TempDir;
+    use std::io::Write;
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn enclosed_paths_stay_inside() {
+        assert_eq!(
+            enclosed_path(Path::new("./img/a.png")),
+            Some(PathBuf::from("img/a.png"))
+        );
+        assert_eq!(enclosed_path(Path::new("../a.png")), None);
+        assert_eq!(enclosed_path(Path::new("img/../../a.png")), None);
+        assert_eq!(enclosed_path(Path::new("/etc/passwd")), None);
+    }
+
+    #[test]
+    fn zip_entries_are_extracted() {
+        let dir = TempDir::new();
+        let archive = dir.0.join("layer.zip");
+        write_zip(&archive, &[("img/a.png", b"a"), ("b.txt", b"b")]);
+
+        let target = dir.0.join("layer");
+        assert_eq!(extract_archive(&archive, &target, 1024).unwrap(), 2);
+        assert_eq!(fs::read(target.join("img/a.png")).unwrap(), b"a");
+        assert_eq!(fs::read(target.join("b.txt")).unwrap(), b"b");
+    }
+
+    #[test]
+    fn zip_entries_escaping_the_target_fail() {
+        let dir = TempDir::new();
+        let archive = dir.0.join("layer.zip");
+        write_zip(&archive, &[("../escaped.txt", b"x")]);
+
+        let target = dir.0.join("layer");
+        assert!(extract_archive(&archive, &target, 1024).is_err());
+        assert!(!dir.0.join("escaped.txt").exists());
+    }
+
+    #[test]
+    fn extraction_stops_at_the_size_limit() {
+        let dir = TempDir::new();
+        let archive = dir.0.join("layer.zip");
+        write_zip(&archive, &[("a.txt", b"1234"), ("b.txt", b"5678")]);
+
+        assert!(extract_archive(&archive, &dir.0.join("exact"), 8).is_ok());
+        assert!(extract_archive(&archive, &dir.0.join("over"), 7).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Attempts each client may make for a name before it has to wait between them, the wait doubles
+/// with every further attempt up to `BACKOFF_MAX_SECS`.
+const FREE_ATTEMPTS: u32 = 3;
+const BACKOFF_MAX_SECS: u64 = 300;
+/// Failed attempts are forgotten after a quiet hour.
+const ATTEMPT_MEMORY_SECS: u64 = 3600;
+
+struct Attempts {
+    count: u32,
+    last: Instant,
+}
+
+impl Attempts {
+    /// How long the client still has to wait before its next attempt.
+    fn get_wait(&self) -> Option<Duration> {
+        let exponent = self.count.checked_sub(FREE_ATTEMPTS)?;
+        let backoff = 2u64.saturating_pow(exponent).min(BACKOFF_MAX_SECS);
+        Duration::from_secs(backoff).checked_sub(self.last.elapsed())
+    }
+}
+
+/// Slows down guessing a secret of a name, such as a PIN or password, per client address. Kept in
+/// memory only.
+#[derive(Default)]
+pub struct Backoff {
+    attempts: Mutex<HashMap<(String, String), Attempts>>,
+}
+
+impl Backoff {
+    /// Counts an attempt before the secret is checked, so parallel attempts cannot all pass the
+    /// same wait. Returns how long to wait instead when the client has to slow down.
+    pub fn try_attempt(&self, name: &str, client: Option<&str>) -> Result<(), Duration> {
+        let mut attempts = self.attempts.lock().unwrap();
+        attempts.retain(|_, attempts| {
+            attempts.last.elapsed() < Duration::from_secs(ATTEMPT_MEMORY_SECS)
+        });
+        let attempts = attempts.entry(get_key(name, client)).or_insert(Attempts {
+            count: 0,
+            last: Instant::now(),
+        });
+        if let Some(wait) = attempts.get_wait() {
+            return Err(wait);
+        }
+        attempts.count += 1;
+        attempts.last = Instant::now();
+        Ok(())
+    }
+
+    /// Forgets the attempts of a client which got the secret right.
+    pub fn reset(&self, name: &str, client: Option<&str>) {
+        self.attempts.lock().unwrap().remove(&get_key(name, client));
+    }
+}
+
+fn get_key(name: &str, client: Option<&str>) -> (String, String) {
+    (name.to_string(), client.unwrap_or_default().to_string())
+}
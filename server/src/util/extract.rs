@@ -4,10 +4,10 @@ use crate::util::AppState;
 use axum::http::StatusCode;
 use std::sync::Arc;
 
-pub fn extract_game<'a>(
-    state: &'a Arc<AppState>,
-    game_id: &'a str,
-) -> Result<&'a GameInfo, (StatusCode, String)> {
+pub fn extract_game(
+    state: &Arc<AppState>,
+    game_id: &str,
+) -> Result<Arc<GameInfo>, (StatusCode, String)> {
     state.registry.get(game_id).ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
@@ -16,11 +16,11 @@ pub fn extract_game<'a>(
     })
 }
 
-pub fn extract_game_instance<'a>(
-    state: &'a Arc<AppState>,
-    game_id: &'a str,
-    instance_id: &'a str,
-) -> Result<(&'a GameInfo, &'a InstanceInfo), (StatusCode, String)> {
+pub fn extract_game_instance(
+    state: &Arc<AppState>,
+    game_id: &str,
+    instance_id: &str,
+) -> Result<(Arc<GameInfo>, Arc<InstanceInfo>), (StatusCode, String)> {
     let game = state.registry.get(game_id).ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
@@ -28,7 +28,7 @@ pub fn extract_game_instance<'a>(
         )
     })?;
 
-    let instance = game.instances.get(instance_id).ok_or_else(|| {
+    let instance = game.get_instance(instance_id).cloned().ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
             format!("no instance found with id {}", instance_id),
@@ -38,11 +38,11 @@ pub fn extract_game_instance<'a>(
     Ok((game, instance))
 }
 
-pub fn extract_game_mod<'a>(
-    state: &'a Arc<AppState>,
-    game_id: &'a str,
-    mod_id: &'a str,
-) -> Result<&'a ModInfo, (StatusCode, String)> {
+pub fn extract_game_mod(
+    state: &Arc<AppState>,
+    game_id: &str,
+    mod_id: &str,
+) -> Result<ModInfo, (StatusCode, String)> {
     let game = state.registry.get(game_id).ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
@@ -50,7 +50,7 @@ pub fn extract_game_mod<'a>(
         )
     })?;
 
-    let mod_info = game.mods.get(mod_id).ok_or_else(|| {
+    let mod_info = game.mods.get(mod_id).cloned().ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
             format!("no mod found with id {}", mod_id),
@@ -1,9 +1,19 @@
 use anyhow::Result;
+use axum::body::Body;
 use axum::http::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH};
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
+use serde::Serialize;
 use std::fs;
-use std::path::Path;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::ToSchema;
+
+const FILE_STREAM_CHUNK_SIZE: usize = 64 * 1024;
 
 pub fn list_filename_limit_extension(
     path: &Path,
@@ -26,20 +36,20 @@ pub fn list_filename_limit_extension(
             continue;
         }
         if let Some(req_ext) = &extension {
-            if let Some(ext) = entry_path.extension() {
-                if ext.to_ascii_lowercase().to_string_lossy().to_string() == req_ext.to_string() {
-                    let file_name = entry_path
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                        .to_string();
-                    let file_stem = entry_path
-                        .file_stem()
-                        .unwrap()
-                        .to_string_lossy()
-                        .to_string();
-                    names.push((file_stem, file_name));
-                }
+            if let Some(ext) = entry_path.extension()
+                && ext.to_ascii_lowercase().to_string_lossy() == *req_ext
+            {
+                let file_name = entry_path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                let file_stem = entry_path
+                    .file_stem()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                names.push((file_stem, file_name));
             }
         } else {
             let file_name = entry_path
@@ -71,40 +81,194 @@ pub fn list_dir_name(path: &Path) -> Result<Vec<String>> {
         let entry = entry?;
         let entry_path = entry.path();
 
-        if entry_path.is_dir() {
-            if let Some(dir_name) = entry_path.file_name() {
-                let dir_name = dir_name.to_string_lossy().to_string();
-                names.push(dir_name);
-            }
+        if entry_path.is_dir()
+            && let Some(dir_name) = entry_path.file_name()
+        {
+            let dir_name = dir_name.to_string_lossy().to_string();
+            names.push(dir_name);
         }
     }
 
     Ok(names)
 }
 
-pub fn etag_hash(content: &Vec<u8>) -> String {
+/// Bytes and number of files below a path.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema)]
+pub struct DiskUsage {
+    pub bytes: u64,
+    pub files: usize,
+}
+
+impl DiskUsage {
+    /// Measures a file or walks a directory, links are not followed and missing paths are empty.
+    pub fn of(path: &Path) -> Result<Self> {
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(DiskUsage::default()),
+            Err(err) => return Err(err.into()),
+        };
+        if !metadata.is_dir() {
+            return Ok(DiskUsage {
+                bytes: metadata.len(),
+                files: usize::from(metadata.is_file()),
+            });
+        }
+
+        let mut usage = DiskUsage::default();
+        for entry in fs::read_dir(path)? {
+            usage.add(DiskUsage::of(&entry?.path())?);
+        }
+        Ok(usage)
+    }
+
+    pub fn add(&mut self, other: DiskUsage) {
+        self.bytes += other.bytes;
+        self.files += other.files;
+    }
+}
+
+/// Whether `name` can be used as a single file or directory name below a managed directory.
+pub fn is_valid_path_segment(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+}
+
+/// Turns a non-empty name into a single path segment which is never hidden. Characters other than
+/// ASCII alphanumerics, `-`, `_`, `@` and `.` after the first character are percent-encoded, so
+/// different names never end up in the same directory.
+pub fn encode_path_segment(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for (index, byte) in name.bytes().enumerate() {
+        if byte.is_ascii_alphanumeric()
+            || matches!(byte, b'-' | b'_' | b'@')
+            || (byte == b'.' && index > 0)
+        {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+pub fn content_hash(content: &[u8]) -> String {
+    format!("{:016x}", xxhash_rust::xxh3::xxh3_64(content))
+}
+
+pub fn etag_hash(content: &[u8]) -> String {
     format!("\"{}\"", xxhash_rust::xxh3::xxh3_64(content))
 }
 
-pub fn etag_check(content: &Vec<u8>, headers: &HeaderMap) -> Option<Response> {
+pub fn etag_check(content: &[u8], headers: &HeaderMap) -> Option<Response> {
     let etag_val = etag_hash(content);
 
-    if let Some(if_not_match) = headers.get(IF_NONE_MATCH) {
-        if let Ok(cli_tag) = if_not_match.to_str() {
-            if cli_tag == etag_val {
-                return Some(
-                    (
-                        StatusCode::NOT_MODIFIED,
-                        [
-                            (CACHE_CONTROL, "public, max-age=31536000"),
-                            (ETAG, etag_val.as_str()),
-                        ],
-                    )
-                        .into_response(),
-                );
+    if let Some(if_not_match) = headers.get(IF_NONE_MATCH)
+        && let Ok(cli_tag) = if_not_match.to_str()
+        && cli_tag == etag_val
+    {
+        return Some(
+            (
+                StatusCode::NOT_MODIFIED,
+                [
+                    (CACHE_CONTROL, "public, max-age=31536000"),
+                    (ETAG, etag_val.as_str()),
+                ],
+            )
+                .into_response(),
+        );
+    }
+
+    None
+}
+
+/// Streams `file` as a response body, read on a blocking thread. `guard` is dropped after the file,
+/// once it was sent or the client went away, e.g. to remove a staged file.
+pub fn stream_file_body<T: Send + 'static>(mut file: fs::File, guard: T) -> Body {
+    let (sender, receiver) = mpsc::channel::<std::io::Result<Vec<u8>>>(4);
+    tokio::task::spawn_blocking(move || {
+        loop {
+            let mut chunk = vec![0; FILE_STREAM_CHUNK_SIZE];
+            match file.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(size) => {
+                    chunk.truncate(size);
+                    if sender.blocking_send(Ok(chunk)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    let _ = sender.blocking_send(Err(err));
+                    break;
+                }
             }
         }
+        drop(file);
+        drop(guard);
+    });
+    Body::from_stream(ReceiverStream::new(receiver))
+}
+
+/// A store file the running server and CLI commands both write. Stores check it before every
+/// access and read it again once its modification time moved, so changes made by the CLI take
+/// effect without a restart and are not overwritten by the server's next write.
+pub struct WatchedFile {
+    path: PathBuf,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl WatchedFile {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = Mutex::new(get_modified(&path));
+        WatchedFile { path, modified }
     }
 
-    None
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file was changed since it was last read or written through [`Self::update`].
+    pub fn is_changed(&self) -> bool {
+        *self.modified.lock().unwrap() != get_modified(&self.path)
+    }
+
+    /// Records the current state of the file as seen, after reading it.
+    pub fn update(&self) {
+        *self.modified.lock().unwrap() = get_modified(&self.path);
+    }
+
+    /// Replaces the file through a temporary file, so a concurrent reader never sees half of it.
+    pub fn write(&self, content: &str) -> std::io::Result<()> {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, &self.path)?;
+        self.update();
+        Ok(())
+    }
+}
+
+fn get_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// A fresh directory under the system temp dir for tests, removed when dropped.
+#[cfg(test)]
+pub struct TempDir(pub PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new() -> Self {
+        let name = format!("server-test-{}", crate::util::token::random_token(8));
+        let path = std::env::temp_dir().join(name);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
 }
@@ -0,0 +1,34 @@
+/// Inserts an inline script right after the opening `<head>` tag, so it runs before the game's own scripts.
+/// Documents without a head get the script prepended. The document is handled as bytes, so it is passed
+/// through untouched whatever its encoding.
+pub fn inject_head_script(html: &[u8], script: &str) -> Vec<u8> {
+    let tag = format!("<script>{}</script>", script);
+    let insert_at = find_head_tag(html).and_then(|start| {
+        html[start..]
+            .iter()
+            .position(|&byte| byte == b'>')
+            .map(|end| start + end + 1)
+    });
+
+    let mut result = Vec::with_capacity(html.len() + tag.len());
+    match insert_at {
+        Some(index) => {
+            result.extend_from_slice(&html[..index]);
+            result.extend_from_slice(tag.as_bytes());
+            result.extend_from_slice(&html[index..]);
+        }
+        None => {
+            result.extend_from_slice(tag.as_bytes());
+            result.extend_from_slice(html);
+        }
+    }
+    result
+}
+
+/// Start of the first `<head>` or `<head ...>` tag in any case, `<header>` is not one.
+fn find_head_tag(html: &[u8]) -> Option<usize> {
+    html.windows(6).position(|window| {
+        window[..5].eq_ignore_ascii_case(b"<head")
+            && (window[5] == b'>' || window[5] == b'/' || window[5].is_ascii_whitespace())
+    })
+}
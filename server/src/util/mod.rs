@@ -1,9 +1,26 @@
+use crate::foundation::api_token::ApiTokenStore;
+use crate::foundation::audit::AuditLog;
+use crate::foundation::event::SaveEventHub;
+use crate::foundation::household::HouseholdStore;
 use crate::foundation::registry::GameRegistry;
+use crate::foundation::share::ShareStore;
+use crate::foundation::user::{SessionStore, UserStore};
 
+pub(crate) mod archive;
+pub(crate) mod backoff;
 pub(crate) mod extract;
 pub(crate) mod file;
+pub(crate) mod inject;
+pub(crate) mod token;
 pub(crate) mod vfs;
 
 pub struct AppState {
     pub registry: GameRegistry,
+    pub shares: ShareStore,
+    pub events: SaveEventHub,
+    pub users: UserStore,
+    pub sessions: SessionStore,
+    pub tokens: ApiTokenStore,
+    pub households: HouseholdStore,
+    pub audit: AuditLog,
 }
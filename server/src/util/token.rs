@@ -0,0 +1,9 @@
+use rand::RngCore;
+use rand::rngs::OsRng;
+
+/// An unguessable hex token made of `bytes` random bytes.
+pub fn random_token(bytes: usize) -> String {
+    let mut buffer = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buffer);
+    buffer.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -52,6 +52,24 @@ pub trait FileSystemTree {
             }
         }
     }
+
+    /// The real paths of all files in the tree.
+    fn get_real_paths(&self) -> Vec<&Path> {
+        let mut paths = Vec::new();
+        Self::collect_real_paths(self.get_root(), &mut paths);
+        paths
+    }
+
+    fn collect_real_paths<'a>(node: &'a FSNode, paths: &mut Vec<&'a Path>) {
+        match node {
+            FSNode::File { real_path } => paths.push(real_path),
+            FSNode::Directory { children } => {
+                for child in children.values() {
+                    Self::collect_real_paths(child, paths);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -82,13 +100,12 @@ impl LayerFS {
             ref mut children, ..
         } = self.root
         {
-            Self::build_tree_recursive(path, path, children)?;
+            Self::build_tree_recursive(path, children)?;
         }
         Ok(())
     }
 
     fn build_tree_recursive(
-        base_path: &Path,
         current_path: &Path,
         children: &mut HashMap<String, FSNode>,
     ) -> Result<()> {
@@ -104,7 +121,7 @@ impl LayerFS {
 
                 if path.is_dir() {
                     let mut dir_children = HashMap::new();
-                    let _ = Self::build_tree_recursive(base_path, &path, &mut dir_children);
+                    let _ = Self::build_tree_recursive(&path, &mut dir_children);
 
                     (
                         file_name.clone(),